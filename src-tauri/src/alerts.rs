@@ -0,0 +1,238 @@
+use crate::error::AppError;
+use crate::types::{
+    AlertMetric, AlertRule, AllUsageData, QuietHours, QuotaForecast, QuotaKind, QuotaLimit,
+    UsageAlert,
+};
+use chrono::{NaiveTime, Timelike};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Firing state of one rule for one quota of one profile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct RuleState {
+    /// Whether the rule may fire; cleared when it fires, set again on re-arm
+    armed: bool,
+    /// Unix seconds of the last alert
+    last_fired_at: Option<i64>,
+}
+
+impl Default for RuleState {
+    fn default() -> Self {
+        Self {
+            armed: true,
+            last_fired_at: None,
+        }
+    }
+}
+
+/// Rule state keyed by rule id, then profile, then quota type
+type EngineState = BTreeMap<String, BTreeMap<String, BTreeMap<String, RuleState>>>;
+
+/// Evaluates alert rules against fresh usage data
+///
+/// Rule state is kept in `alert_state.json` next to `config.json`, so an
+/// alert that already fired stays quiet across refreshes and restarts.
+pub struct AlertEngine {
+    path: PathBuf,
+    state: Mutex<EngineState>,
+}
+
+impl AlertEngine {
+    /// Create an engine backed by the given state file
+    pub fn new(path: PathBuf) -> Self {
+        let state = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+
+        Self {
+            path,
+            state: Mutex::new(state),
+        }
+    }
+
+    /// Create an engine in the default app data directory
+    pub fn open_default() -> Self {
+        Self::new(crate::config::get_data_dir().join("alert_state.json"))
+    }
+
+    /// Apply every rule to every matching quota of `data` and return the alerts to emit
+    ///
+    /// `burn_rate` rules only apply to the quota the forecast was made for.
+    pub fn evaluate(
+        &self,
+        rules: &[AlertRule],
+        data: &AllUsageData,
+        forecast: Option<&QuotaForecast>,
+        now: i64,
+    ) -> Result<Vec<UsageAlert>, AppError> {
+        let mut state = self.state.lock().unwrap();
+        let before = state.clone();
+        let mut alerts = Vec::new();
+
+        for rule in rules {
+            for limit in data.quota_limits.iter().filter(|l| l.kind.matches(&rule.quota_type)) {
+                let Some(value) = metric_value(rule.metric, limit, forecast) else {
+                    continue;
+                };
+
+                let entry = state
+                    .entry(rule.id.clone())
+                    .or_default()
+                    .entry(data.profile.clone())
+                    .or_default()
+                    .entry(limit.kind.as_raw().to_string())
+                    .or_default();
+
+                if !entry.armed {
+                    if crossed(rule.metric, value, rule.rearm_at.unwrap_or(rule.threshold), false) {
+                        entry.armed = true;
+                    }
+                    continue;
+                }
+
+                if !crossed(rule.metric, value, rule.threshold, true) {
+                    continue;
+                }
+
+                // Stay armed so the alert fires once the quiet period or cooldown is over
+                let cooling_down = entry
+                    .last_fired_at
+                    .is_some_and(|fired| now - fired < rule.cooldown_minutes as i64 * 60);
+                if cooling_down || rule.quiet_hours.as_ref().is_some_and(|q| is_quiet(q, now)) {
+                    continue;
+                }
+
+                entry.armed = false;
+                entry.last_fired_at = Some(now);
+                alerts.push(UsageAlert {
+                    profile: data.profile.clone(),
+                    rule_id: rule.id.clone(),
+                    quota: limit.kind.clone(),
+                    label: crate::labels::quota_label(limit),
+                    metric: rule.metric,
+                    value,
+                    threshold: rule.threshold,
+                    percentage: limit.percentage,
+                    severity: rule.severity,
+                    timestamp: now,
+                });
+            }
+        }
+
+        if *state != before {
+            self.write(&state)?;
+        }
+        Ok(alerts)
+    }
+
+    /// Forget state of rules that no longer exist, e.g. after the rules were edited
+    pub fn retain_rules(&self, rules: &[AlertRule]) -> Result<(), AppError> {
+        let ids: BTreeSet<&str> = rules.iter().map(|r| r.id.as_str()).collect();
+        let mut state = self.state.lock().unwrap();
+        let before = state.len();
+
+        state.retain(|id, _| ids.contains(id.as_str()));
+
+        if state.len() != before {
+            self.write(&state)?;
+        }
+        Ok(())
+    }
+
+    fn write(&self, state: &EngineState) -> Result<(), AppError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| AppError::Storage(format!("Failed to create alert state directory: {}", e)))?;
+        }
+
+        let contents = serde_json::to_string_pretty(state)
+            .map_err(|e| AppError::Storage(format!("Failed to serialize alert state: {}", e)))?;
+        fs::write(&self.path, contents)
+            .map_err(|e| AppError::Storage(format!("Failed to write alert state: {}", e)))
+    }
+}
+
+/// Check rules for mistakes before they are saved
+pub fn validate_rules(rules: &[AlertRule]) -> Result<(), AppError> {
+    let mut ids = BTreeSet::new();
+
+    for rule in rules {
+        if rule.id.trim().is_empty() {
+            return Err(AppError::InvalidInput("Alert rule id cannot be empty".to_string()));
+        }
+        if !ids.insert(rule.id.as_str()) {
+            return Err(AppError::InvalidInput(format!("Duplicate alert rule id: {}", rule.id)));
+        }
+        if !rule.threshold.is_finite() || rule.rearm_at.is_some_and(|r| !r.is_finite()) {
+            return Err(AppError::InvalidInput(format!("Alert rule {} needs a numeric threshold", rule.id)));
+        }
+        // A re-arm level past the threshold would re-arm right away and fire on every refresh
+        if let Some(rearm_at) = rule.rearm_at {
+            let falling = rule.metric == AlertMetric::Remaining;
+            if (falling && rearm_at < rule.threshold) || (!falling && rearm_at > rule.threshold) {
+                return Err(AppError::InvalidInput(format!(
+                    "Alert rule {} must re-arm {} its threshold",
+                    rule.id,
+                    if falling { "above" } else { "below" }
+                )));
+            }
+        }
+        if let Some(quiet) = &rule.quiet_hours {
+            parse_time(&quiet.start)?;
+            parse_time(&quiet.end)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Current value of `metric` for a quota, if it is available
+fn metric_value(metric: AlertMetric, limit: &QuotaLimit, forecast: Option<&QuotaForecast>) -> Option<f64> {
+    match metric {
+        AlertMetric::Percentage => Some(limit.percentage),
+        AlertMetric::Remaining => limit.remaining.map(|r| r as f64),
+        // The forecast only covers the token quota
+        AlertMetric::BurnRate => forecast
+            .filter(|_| limit.kind == QuotaKind::Tokens)
+            .map(|f| f.burn_rate_per_hour),
+    }
+}
+
+/// Whether `value` is past `level` in the alerting direction (`towards_alert`)
+/// or back on the safe side of it
+fn crossed(metric: AlertMetric, value: f64, level: f64, towards_alert: bool) -> bool {
+    let falling = metric == AlertMetric::Remaining;
+    match (falling, towards_alert) {
+        (false, true) => value >= level,
+        (false, false) => value < level,
+        (true, true) => value <= level,
+        (true, false) => value > level,
+    }
+}
+
+/// Whether the local time at `now` falls inside the quiet hours
+fn is_quiet(quiet: &QuietHours, now: i64) -> bool {
+    let (Ok(start), Ok(end)) = (parse_time(&quiet.start), parse_time(&quiet.end)) else {
+        return false;
+    };
+    let Some(time) = chrono::DateTime::from_timestamp(now, 0)
+        .map(|date| date.with_timezone(&chrono::Local).time().with_second(0).unwrap_or_default())
+    else {
+        return false;
+    };
+
+    if start <= end {
+        start <= time && time < end
+    } else {
+        time >= start || time < end
+    }
+}
+
+fn parse_time(value: &str) -> Result<NaiveTime, AppError> {
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .map_err(|_| AppError::InvalidInput(format!("Invalid time {:?}, expected HH:MM", value)))
+}
@@ -1,14 +1,120 @@
 use crate::types::{
-    Config, ModelUsageItem, ModelUsageResponse, ToolUsageItem, ToolUsageResponse, ToolDetail,
-    QuotaLimit, QuotaLimitResponse, ModelUsageData, ModelUsageResult, ModelUsageTimeSeries,
+    ApiResponse, BaseUrlValidation, Config, EndpointProbe, ModelUsageItem, ToolUsageData, ToolUsageItem, ToolDetail,
+    QuotaKind, QuotaLimit, QuotaLimitData, ModelUsageData, ModelUsageResult, ModelUsageTimeSeries,
+    TimeWindow, RetryPolicy, AllUsageData, SectionStatus,
 };
-use chrono::{DateTime, Local, Duration, Timelike};
-use reqwest::Client;
+use crate::error::AppError;
+use crate::retry::with_retry;
+use chrono::{DateTime, Local, Duration, TimeZone, Timelike};
+use futures::future::join_all;
+use std::collections::BTreeMap;
+use std::time::{Duration as StdDuration, Instant};
+use reqwest::{Client, StatusCode};
+use serde::de::{DeserializeOwned, IgnoredAny};
+use url::Url;
+
+/// Most models fetched individually per refresh, each costing one extra request
+pub const MAX_MODEL_BREAKDOWN: usize = 5;
+
+/// Characters of a response body kept in error messages and logs
+const BODY_EXCERPT_CHARS: usize = 200;
+
+/// Results of fetching every section, each of which may fail independently
+pub struct FetchResults {
+    pub quota: Result<Vec<QuotaLimit>, AppError>,
+    pub models: Result<ModelUsageResult, AppError>,
+    pub tools: Result<Vec<ToolUsageItem>, AppError>,
+}
+
+impl FetchResults {
+    /// Combine the sections into a snapshot, falling back to `previous` for failed ones
+    ///
+    /// Fails only when every section failed. The previous quota limits are
+    /// window-independent and always usable; its model and tool data only
+    /// when it covers the same window. Fallback sections keep their error
+    /// and last fetch time in the snapshot's status.
+    pub fn into_usage_data(
+        self,
+        previous: Option<&AllUsageData>,
+        profile: &str,
+        window: TimeWindow,
+        now: i64,
+    ) -> Result<AllUsageData, AppError> {
+        let FetchResults { quota, models, tools } = self;
+        if let (Err(e), Err(_), Err(_)) = (&quota, &models, &tools) {
+            return Err(e.clone());
+        }
+
+        let mut status = previous.map(|p| p.status.clone()).unwrap_or_default();
+        let quota_limits = merge_section(
+            quota,
+            previous.map(|p| p.quota_limits.clone()),
+            &mut status.quota,
+            now,
+        );
+
+        // Model and tool data from a different window would be misleading
+        let same_window = previous.filter(|p| p.time_window == window);
+        let models = merge_section(
+            models,
+            same_window.map(|p| ModelUsageResult {
+                items: p.model_usage.clone(),
+                timeseries: p.model_usage_timeseries.clone(),
+                timeseries_by_model: p.model_usage_timeseries_by_model.clone(),
+            }),
+            &mut status.models,
+            now,
+        );
+        let tool_usage = merge_section(
+            tools,
+            same_window.map(|p| p.tool_usage.clone()),
+            &mut status.tools,
+            now,
+        );
+
+        Ok(AllUsageData {
+            model_usage: models.items,
+            model_usage_timeseries: models.timeseries,
+            model_usage_timeseries_by_model: models.timeseries_by_model,
+            tool_usage,
+            quota_limits,
+            timestamp: now,
+            time_window: window,
+            status,
+            profile: profile.to_string(),
+        })
+    }
+}
+
+/// Use a freshly fetched section, or fall back to the previous data and mark it stale
+fn merge_section<T: Default>(
+    result: Result<T, AppError>,
+    previous: Option<T>,
+    status: &mut SectionStatus,
+    now: i64,
+) -> T {
+    match result {
+        Ok(value) => {
+            status.fetched_at = Some(now);
+            status.error = None;
+            value
+        }
+        Err(e) => {
+            tracing::warn!(error = %e, "Section refresh failed");
+            status.error = Some((&e).into());
+            if previous.is_none() {
+                status.fetched_at = None;
+            }
+            previous.unwrap_or_default()
+        }
+    }
+}
 
 /// HTTP client for Z.ai API
 pub struct UsageClient {
     client: Client,
     config: Config,
+    retry_policy: RetryPolicy,
 }
 
 impl UsageClient {
@@ -16,146 +122,379 @@ impl UsageClient {
     pub fn new(config: Config) -> Self {
         Self {
             client: Client::new(),
+            retry_policy: config.retry.clone(),
             config,
         }
     }
 
-    /// Create ModelUsageItem from ModelUsageData
-    fn create_model_usage_items(data: &ModelUsageData) -> Vec<ModelUsageItem> {
-        vec![ModelUsageItem {
-            model: "All Models".to_string(),
+    /// Fail requests that take longer than `timeout` with a network error
+    ///
+    /// Errors if the HTTP client can't be built, rather than silently
+    /// falling back to one without the timeout.
+    pub fn with_timeout(mut self, timeout: StdDuration) -> Result<Self, AppError> {
+        self.client = Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| AppError::Network(format!("Failed to build HTTP client: {}", e)))?;
+        Ok(self)
+    }
+
+    /// Create a ModelUsageItem from the totals in ModelUsageData
+    fn create_model_usage_item(model: &str, data: &ModelUsageData) -> ModelUsageItem {
+        ModelUsageItem {
+            model: model.to_string(),
             token_count: data.total_usage.total_tokens_usage,
             request_count: data.total_usage.total_model_call_count,
-        }]
+        }
+    }
+
+    /// Extract time-series data for charts from ModelUsageData
+    fn create_timeseries(data: ModelUsageData) -> ModelUsageTimeSeries {
+        ModelUsageTimeSeries {
+            x_time: data.x_time,
+            model_call_count: data.model_call_count,
+            tokens_usage: data.tokens_usage,
+        }
     }
 
-    /// Extract the base domain from the configured base URL
-    fn get_base_domain(&self) -> Result<String, String> {
-        let base_url = &self.config.base_url;
+    /// Collect the model codes listed in the token quota's usage details
+    ///
+    /// Keeps the `MAX_MODEL_BREAKDOWN` codes with the most usage, sorted by name.
+    pub fn model_codes(limits: &[QuotaLimit]) -> Vec<String> {
+        let mut details: Vec<(&str, i64)> = limits
+            .iter()
+            .filter(|l| l.kind == QuotaKind::Tokens)
+            .filter_map(|l| l.usage_details.as_ref())
+            .flatten()
+            .map(|detail| (detail.tool_name.as_str(), detail.usage))
+            .collect();
+        details.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
 
-        if base_url.contains("api.z.ai") {
-            Ok("https://api.z.ai".to_string())
-        } else if base_url.contains("open.bigmodel.cn") || base_url.contains("dev.bigmodel.cn") {
-            Ok("https://open.bigmodel.cn".to_string())
-        } else {
-            Err("Unrecognized base URL".to_string())
+        let mut codes: Vec<String> = Vec::new();
+        for (code, _) in details {
+            if codes.len() == MAX_MODEL_BREAKDOWN {
+                break;
+            }
+            if !codes.iter().any(|c| c == code) {
+                codes.push(code.to_string());
+            }
+        }
+        codes.sort();
+        codes
+    }
+
+    /// Root of the monitor endpoints for a config
+    ///
+    /// `monitor_base_url` is used as given when set; otherwise the root is derived from `base_url`.
+    pub fn monitor_root(config: &Config) -> Result<String, AppError> {
+        match config.monitor_base_url.as_deref().filter(|u| !u.trim().is_empty()) {
+            Some(monitor_base_url) => {
+                let url = parse_http_url(monitor_base_url)?;
+                Ok(url.as_str().trim_end_matches('/').to_string())
+            }
+            None => Self::base_domain(&config.base_url),
         }
     }
 
+    /// Map a base URL to the root serving the monitor endpoints
+    ///
+    /// Z.ai and BigModel hosts map to their API domain. Any other URL keeps its
+    /// scheme, host, port and the path before its `/api` segment, so
+    /// "https://proxy.example.com/zai/api/anthropic" becomes "https://proxy.example.com/zai".
+    pub fn base_domain(base_url: &str) -> Result<String, AppError> {
+        let url = parse_http_url(base_url)?;
+        let host = url.host_str().unwrap_or_default();
+
+        if host == "api.z.ai" {
+            return Ok("https://api.z.ai".to_string());
+        }
+        if host == "open.bigmodel.cn" || host == "dev.bigmodel.cn" {
+            return Ok("https://open.bigmodel.cn".to_string());
+        }
+
+        let segments: Vec<&str> = url.path().split('/').filter(|s| !s.is_empty()).collect();
+        let prefix = segments
+            .iter()
+            .position(|segment| *segment == "api")
+            .map(|index| &segments[..index])
+            .unwrap_or(&segments[..]);
+
+        let mut root = url.origin().ascii_serialization();
+        for segment in prefix {
+            root.push('/');
+            root.push_str(segment);
+        }
+        Ok(root)
+    }
+
+    /// Fetch every monitor endpoint once and report which ones work
+    ///
+    /// Only fails if no monitor root can be derived; endpoint failures are
+    /// part of the report.
+    pub async fn probe_endpoints(&self) -> Result<BaseUrlValidation, AppError> {
+        let monitor_root = Self::monitor_root(&self.config)?;
+        let window = TimeWindow::default();
+
+        let (quota, models, tools) = tokio::join!(
+            timed(self.fetch_quota_limits()),
+            timed(self.fetch_model_usage_data(&window, None)),
+            timed(self.fetch_tool_usage(&window)),
+        );
+
+        let probe = |endpoint: &str, path: &str, (result, duration_ms): (Result<(), AppError>, u64)| EndpointProbe {
+            endpoint: endpoint.to_string(),
+            url: format!("{}{}", monitor_root, path),
+            ok: result.is_ok(),
+            status: match &result {
+                Ok(()) => Some(200),
+                Err(e) => e.status(),
+            },
+            error: result.err().map(|e| e.to_string()),
+            duration_ms,
+        };
+
+        let endpoints = vec![
+            probe("quota", "/api/monitor/usage/quota/limit", quota),
+            probe("model_usage", "/api/monitor/usage/model-usage", models),
+            probe("tool_usage", "/api/monitor/usage/tool-usage", tools),
+        ];
+        Ok(BaseUrlValidation { monitor_root, endpoints })
+    }
+
     /// Calculate the time window for API queries
     /// Returns (start_time, end_time) as formatted strings
     ///
-    /// Time window: from yesterday at the current hour (HH:00:00)
-    /// to today at the current hour end (HH:59:59)
-    fn get_time_window() -> (String, String) {
+    /// Rolling windows start at the top of the hour N hours/days ago and end
+    /// at the end of the current hour (HH:59:59), matching the API's hourly buckets.
+    fn get_time_window(window: &TimeWindow) -> Result<(String, String), AppError> {
         let now = Local::now();
 
-        // Start: yesterday at current hour HH:00:00
-        let start_date = now - Duration::days(1);
-        let start_date = start_date
-            .with_second(0)
-            .unwrap()
-            .with_minute(0)
-            .unwrap()
-            .with_hour(now.hour())
-            .unwrap();
+        let start_of_hour = |date: DateTime<Local>| -> DateTime<Local> {
+            date.with_second(0)
+                .unwrap()
+                .with_minute(0)
+                .unwrap()
+        };
+
+        // Local midnight can be skipped by a DST change; start at the first hour that exists
+        let start_of_day = |date: DateTime<Local>| -> DateTime<Local> {
+            (0..24)
+                .filter_map(|hour| date.date_naive().and_hms_opt(hour, 0, 0))
+                .find_map(|start| Local.from_local_datetime(&start).earliest())
+                .unwrap_or_else(|| start_of_hour(date))
+        };
 
         // End: today at current hour HH:59:59
-        let end_date = now
+        let end_of_hour = now
             .with_second(59)
             .unwrap()
             .with_minute(59)
-            .unwrap()
-            .with_hour(now.hour())
             .unwrap();
 
+        let (start_date, end_date) = match window {
+            TimeWindow::Last24Hours => (start_of_hour(now - Duration::days(1)), end_of_hour),
+            TimeWindow::Last5Hours => (start_of_hour(now - Duration::hours(5)), end_of_hour),
+            TimeWindow::Today => (start_of_day(now), end_of_hour),
+            TimeWindow::Last7Days => (start_of_hour(now - Duration::days(7)), end_of_hour),
+            TimeWindow::Last30Days => (start_of_hour(now - Duration::days(30)), end_of_hour),
+            TimeWindow::Custom { start, end } => {
+                if start >= end {
+                    return Err(AppError::InvalidInput(
+                        "Custom time window start must be before end".to_string(),
+                    ));
+                }
+                let to_local = |ts: i64| -> Result<DateTime<Local>, AppError> {
+                    DateTime::from_timestamp(ts, 0)
+                        .map(|date| date.with_timezone(&Local))
+                        .ok_or_else(|| AppError::InvalidInput(format!("Invalid timestamp: {}", ts)))
+                };
+                (to_local(*start)?, to_local(*end)?)
+            }
+        };
+
         let format_datetime = |date: DateTime<Local>| -> String {
             date.format("%Y-%m-%d %H:%M:%S").to_string()
         };
 
-        (format_datetime(start_date), format_datetime(end_date))
+        Ok((format_datetime(start_date), format_datetime(end_date)))
     }
 
-    /// Fetch model usage data from the API
-    pub async fn fetch_model_usage(&self) -> Result<ModelUsageResult, String> {
-        let base_domain = self.get_base_domain()?;
-        let url = format!("{}/api/monitor/usage/model-usage", base_domain);
-        let (start, end) = Self::get_time_window();
+    /// Send an authenticated GET request, retrying per the configured policy
+    async fn send_request(&self, url: &str, query: &[(&str, &str)]) -> Result<String, AppError> {
+        with_retry(&self.retry_policy, || self.send_once(url, query)).await
+    }
 
+    /// Send a single authenticated GET request and return the raw body of a 200 response
+    ///
+    /// Maps 401/403 to `Unauthorized`, 429 to `RateLimited` and any other
+    /// non-200 status to `Http`.
+    async fn send_once(&self, url: &str, query: &[(&str, &str)]) -> Result<String, AppError> {
         let response = self
             .client
-            .get(&url)
-            .query(&[("startTime", &start), ("endTime", &end)])
+            .get(url)
+            .query(query)
             .header("Authorization", &self.config.auth_token)
             .header("Accept-Language", "en-US,en")
             .header("Content-Type", "application/json")
             .send()
-            .await
-            .map_err(|e| format!("Request failed: {}", e))?;
+            .await?;
 
         let status = response.status();
-        if status != 200 {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(format!("HTTP {}: {}", status, error_text));
+        if status != StatusCode::OK {
+            let retry_after_secs = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(parse_retry_after);
+            let error_text = excerpt(&response.text().await.unwrap_or_default());
+            tracing::debug!(url, status = status.as_u16(), body = %error_text, "Monitor API request failed");
+
+            return Err(match status {
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => AppError::Unauthorized {
+                    status: status.as_u16(),
+                },
+                StatusCode::TOO_MANY_REQUESTS => AppError::RateLimited { retry_after_secs },
+                _ => AppError::Http {
+                    status: status.as_u16(),
+                    body: error_text,
+                    retry_after_secs,
+                },
+            });
         }
 
-        // Log raw response body for debugging
-        let raw_body = response.text().await.map_err(|e| format!("Failed to read response body: {}", e))?;
-        eprintln!("DEBUG: Model usage API response: {}", raw_body);
+        let body = response
+            .text()
+            .await
+            .map_err(|e| AppError::Network(format!("Failed to read response body: {}", e)))?;
+        tracing::debug!(url, bytes = body.len(), body = %excerpt(&body), "Monitor API response");
+        Ok(body)
+    }
+
+    /// Fetch quota limits, per-model usage and tool usage
+    ///
+    /// Model codes for the per-model breakdown come from the quota response,
+    /// so quota and models are chained while tools run alongside them.
+    pub async fn fetch_all(&self, window: &TimeWindow) -> FetchResults {
+        let quota_and_models = async {
+            let quota = self.fetch_quota_limits().await;
+            let model_codes = quota
+                .as_deref()
+                .map(Self::model_codes)
+                .unwrap_or_default();
+            let models = self.fetch_model_usage(window, &model_codes).await;
+            (quota, models)
+        };
 
-        let model_response: ModelUsageResponse = serde_json::from_str(&raw_body)
-            .map_err(|e| format!("Parse error: {} - Response was: {}", e, raw_body))?;
+        let ((quota, models), tools) = tokio::join!(quota_and_models, self.fetch_tool_usage(window));
 
-        // Convert time-series data to ModelUsageItem format for frontend
-        // Since API returns totals, create a single "All Models" entry
-        let model_items = Self::create_model_usage_items(&model_response.data);
+        FetchResults { quota, models, tools }
+    }
 
-        // Extract time-series data for charts
-        let timeseries = ModelUsageTimeSeries {
-            x_time: model_response.data.x_time,
-            model_call_count: model_response.data.model_call_count,
-            tokens_usage: model_response.data.tokens_usage,
-        };
+    /// Fetch model usage data from the API for the given time window
+    ///
+    /// Returns one item per entry in `model_codes`, each fetched with the
+    /// `modelCode` query parameter, alongside the account-wide time series.
+    /// That is one request for the account plus one per model.
+    ///
+    /// A single "All Models" item with the account totals is returned instead
+    /// when there are no model codes, every per-model request fails, or the
+    /// server ignores `modelCode` and answers each model with the account total.
+    /// A lone model that really accounts for all usage is therefore shown as
+    /// "All Models" too, with the same figures.
+    pub async fn fetch_model_usage(
+        &self,
+        window: &TimeWindow,
+        model_codes: &[String],
+    ) -> Result<ModelUsageResult, AppError> {
+        let total = self.fetch_model_usage_data(window, None).await?;
+
+        let per_model = join_all(
+            model_codes
+                .iter()
+                .map(|code| self.fetch_model_usage_data(window, Some(code))),
+        )
+        .await;
+
+        let mut items = Vec::new();
+        let mut timeseries_by_model = BTreeMap::new();
+        let mut all_match_total = true;
+        for (code, result) in model_codes.iter().zip(per_model) {
+            match result {
+                Ok(data) => {
+                    all_match_total &= Self::same_usage(&data, &total);
+                    items.push(Self::create_model_usage_item(code, &data));
+                    timeseries_by_model.insert(code.clone(), Self::create_timeseries(data));
+                }
+                // One unavailable model should not hide the others
+                Err(e) => tracing::warn!(model = %code, error = %e, "Failed to fetch model usage"),
+            }
+        }
+
+        // Filtered figures can't add up to more than the account total, and
+        // answers identical to it are the unfiltered figures
+        let per_model_tokens: i64 = items.iter().map(|item| item.token_count).sum();
+        let has_usage = total.total_usage.total_tokens_usage > 0 || total.total_usage.total_model_call_count > 0;
+        let ignored = per_model_tokens > total.total_usage.total_tokens_usage || (has_usage && all_match_total);
+        if !items.is_empty() && ignored {
+            tracing::debug!("The model usage endpoint ignored modelCode; showing account totals only");
+            items.clear();
+            timeseries_by_model.clear();
+        }
+
+        if items.is_empty() {
+            items.push(Self::create_model_usage_item("All Models", &total));
+        }
 
         Ok(ModelUsageResult {
-            items: model_items,
-            timeseries: Some(timeseries),
+            items,
+            timeseries: Some(Self::create_timeseries(total)),
+            timeseries_by_model,
         })
     }
 
-    /// Fetch tool usage data from the API
-    pub async fn fetch_tool_usage(&self) -> Result<Vec<ToolUsageItem>, String> {
-        let base_domain = self.get_base_domain()?;
-        let url = format!("{}/api/monitor/usage/tool-usage", base_domain);
-        let (start, end) = Self::get_time_window();
+    /// Whether two model usage answers carry the same totals and time series
+    fn same_usage(a: &ModelUsageData, b: &ModelUsageData) -> bool {
+        a.total_usage.total_tokens_usage == b.total_usage.total_tokens_usage
+            && a.total_usage.total_model_call_count == b.total_usage.total_model_call_count
+            && a.x_time == b.x_time
+            && a.tokens_usage == b.tokens_usage
+            && a.model_call_count == b.model_call_count
+    }
 
-        let response = self
-            .client
-            .get(&url)
-            .query(&[("startTime", &start), ("endTime", &end)])
-            .header("Authorization", &self.config.auth_token)
-            .header("Accept-Language", "en-US,en")
-            .header("Content-Type", "application/json")
-            .send()
-            .await
-            .map_err(|e| format!("Request failed: {}", e))?;
+    /// Fetch model usage for the whole account, or a single model if `model_code` is set
+    async fn fetch_model_usage_data(
+        &self,
+        window: &TimeWindow,
+        model_code: Option<&str>,
+    ) -> Result<ModelUsageData, AppError> {
+        let base_domain = Self::monitor_root(&self.config)?;
+        let url = format!("{}/api/monitor/usage/model-usage", base_domain);
+        let (start, end) = Self::get_time_window(window)?;
 
-        let status = response.status();
-        if status != 200 {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(format!("HTTP {}: {}", status, error_text));
+        let mut query = vec![("startTime", start.as_str()), ("endTime", end.as_str())];
+        if let Some(code) = model_code {
+            query.push(("modelCode", code));
         }
 
-        // Log raw response body for debugging
-        let raw_body = response.text().await.map_err(|e| format!("Failed to read response body: {}", e))?;
-        eprintln!("DEBUG: Tool usage API response: {}", raw_body);
+        let raw_body = self.send_request(&url, &query).await?;
+
+        decode(&raw_body)
+    }
+
+    /// Fetch tool usage data from the API for the given time window
+    pub async fn fetch_tool_usage(&self, window: &TimeWindow) -> Result<Vec<ToolUsageItem>, AppError> {
+        let base_domain = Self::monitor_root(&self.config)?;
+        let url = format!("{}/api/monitor/usage/tool-usage", base_domain);
+        let (start, end) = Self::get_time_window(window)?;
+
+        let raw_body = self
+            .send_request(&url, &[("startTime", &start), ("endTime", &end)])
+            .await?;
 
-        let tool_response: ToolUsageResponse = serde_json::from_str(&raw_body)
-            .map_err(|e| format!("Parse error: {} - Response was: {}", e, raw_body))?;
+        let tool_data: ToolUsageData = decode(&raw_body)?;
 
         // Convert ToolDetail to ToolUsageItem format for frontend
-        let tool_items: Vec<ToolUsageItem> = tool_response
-            .data
+        let tool_items: Vec<ToolUsageItem> = tool_data
             .total_usage
             .tool_details
             .into_iter()
@@ -169,47 +508,73 @@ impl UsageClient {
     }
 
     /// Fetch quota limits from the API
-    pub async fn fetch_quota_limits(&self) -> Result<Vec<QuotaLimit>, String> {
-        let base_domain = self.get_base_domain()?;
+    pub async fn fetch_quota_limits(&self) -> Result<Vec<QuotaLimit>, AppError> {
+        let base_domain = Self::monitor_root(&self.config)?;
         let url = format!("{}/api/monitor/usage/quota/limit", base_domain);
 
-        let response = self
-            .client
-            .get(&url)
-            .header("Authorization", &self.config.auth_token)
-            .header("Accept-Language", "en-US,en")
-            .header("Content-Type", "application/json")
-            .send()
-            .await
-            .map_err(|e| format!("Request failed: {}", e))?;
+        let raw_body = self.send_request(&url, &[]).await?;
 
-        let status = response.status();
-        if status != 200 {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(format!("HTTP {}: {}", status, error_text));
-        }
+        let quota_data: QuotaLimitData = decode(&raw_body)?;
 
-        // Log raw response body for debugging
-        let raw_body = response.text().await.map_err(|e| format!("Failed to read response body: {}", e))?;
-        eprintln!("DEBUG: Quota limits API response: {}", raw_body);
+        let mut limits = quota_data.limits;
+        for limit in &mut limits {
+            limit.decode();
+        }
 
-        let quota_response: QuotaLimitResponse = serde_json::from_str(&raw_body)
-            .map_err(|e| format!("Parse error: {} - Response was: {}", e, raw_body))?;
+        Ok(limits)
+    }
+}
 
-        // Transform quota limits (match Node.js logic from query-usage.mjs)
-        let mut limits = quota_response.data.limits;
-        for limit in &mut limits {
-            match limit.type_field.as_str() {
-                "TOKENS_LIMIT" => {
-                    limit.type_field = "Token usage(5 Hour)".to_string();
-                }
-                "TIME_LIMIT" => {
-                    limit.type_field = "MCP usage(1 Month)".to_string();
-                }
-                _ => {}
+/// Parse an API envelope and return its payload
+///
+/// Business failures become `AppError::Api` with the server's message, even
+/// when their `data` is missing or doesn't match `T`.
+fn decode<T: DeserializeOwned>(raw_body: &str) -> Result<T, AppError> {
+    match serde_json::from_str::<ApiResponse<T>>(raw_body) {
+        Ok(response) => response.into_data(),
+        Err(e) => {
+            if let Ok(response) = serde_json::from_str::<ApiResponse<IgnoredAny>>(raw_body) {
+                response.check()?;
             }
+            Err(AppError::Parse(format!("{} - Response began: {}", e, excerpt(raw_body))))
         }
+    }
+}
 
-        Ok(limits)
+/// The start of a response body, so errors and logs never carry a whole payload
+pub(crate) fn excerpt(body: &str) -> String {
+    let body = body.trim();
+    match body.char_indices().nth(BODY_EXCERPT_CHARS) {
+        Some((end, _)) => format!("{}…", &body[..end]),
+        None => body.to_string(),
+    }
+}
+
+/// Parse an absolute http(s) URL
+fn parse_http_url(value: &str) -> Result<Url, AppError> {
+    let url = Url::parse(value.trim())
+        .map_err(|e| AppError::InvalidBaseUrl(format!("{} ({})", value, e)))?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return Err(AppError::InvalidBaseUrl(format!("{} (expected an http or https URL)", value)));
     }
+    Ok(url)
+}
+
+/// Run a request, discarding its value, and measure how long it took
+async fn timed<T>(request: impl std::future::Future<Output = Result<T, AppError>>) -> (Result<(), AppError>, u64) {
+    let started = Instant::now();
+    let result = request.await.map(|_| ());
+    (result, started.elapsed().as_millis() as u64)
+}
+
+/// Parse a `Retry-After` header given either as seconds or as an HTTP date
+pub(crate) fn parse_retry_after(value: &str) -> Option<u64> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(secs);
+    }
+
+    DateTime::parse_from_rfc2822(value)
+        .ok()
+        .map(|date| (date.timestamp() - Local::now().timestamp()).max(0) as u64)
 }
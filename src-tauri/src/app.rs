@@ -0,0 +1,390 @@
+use crate::alerts::AlertEngine;
+use crate::commands::AppState;
+use crate::config::{load_layered_config, ConfigLayers};
+use crate::error::AppError;
+use crate::history::HistoryStore;
+use crate::metrics::MetricsServer;
+use crate::notifications::{NotificationCenter, SystemNotifier};
+use crate::scheduler::Scheduler;
+use crate::tray_title::{TitleContext, TrayTemplate};
+use crate::webhooks::WebhookDispatcher;
+use crate::types::{AllUsageData, Config, QuotaForecast, QuotaKind, TimeWindow};
+use std::collections::BTreeMap;
+use tauri::{
+    menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu},
+    tray::{TrayIconBuilder, TrayIconEvent},
+    Manager, AppHandle, Runtime,
+};
+use crate::{commands, forecast, logging, scheduler, tray_icon};
+
+/// Token and MCP quota percentages of a usage snapshot
+fn quota_percentages(usage_data: &AllUsageData) -> (f64, f64) {
+    let token_pct = usage_data.quota(&QuotaKind::Tokens).map(|l| l.percentage).unwrap_or(0.0);
+    let mcp_pct = usage_data.quota(&QuotaKind::Time).map(|l| l.percentage).unwrap_or(0.0);
+
+    (token_pct, mcp_pct)
+}
+
+/// Highest token and MCP percentages across `usage_data` and `others`
+fn worst_case_percentages(usage_data: &AllUsageData, others: &[&AllUsageData]) -> (f64, f64) {
+    others.iter()
+        .map(|data| quota_percentages(data))
+        .fold(quota_percentages(usage_data), |(t, m), (ot, om)| (t.max(ot), m.max(om)))
+}
+
+/// Generate tray title with current usage statistics
+///
+/// When `others` is non-empty the title shows the worst-case percentages
+/// across the active profile and all of them.
+fn generate_tray_title(
+    template: &TrayTemplate,
+    usage_data: &AllUsageData,
+    others: &[&AllUsageData],
+    forecast: Option<&QuotaForecast>,
+) -> String {
+    let percentages = worst_case_percentages(usage_data, others);
+
+    template.render(&TitleContext {
+        data: usage_data,
+        percentages,
+        forecast,
+        now: chrono::Local::now().timestamp(),
+    })
+}
+
+/// "Resets in HH:MM" for the token quota, if it has an upcoming reset
+fn reset_countdown(usage_data: &AllUsageData) -> Option<String> {
+    let remaining = forecast::token_reset_at(usage_data)? - chrono::Local::now().timestamp();
+    (remaining > 0).then(|| format!("Resets in {}", forecast::format_countdown(remaining)))
+}
+
+/// Generate tooltip text with current usage statistics
+///
+/// With more than one profile, a line per profile is added.
+fn generate_tray_tooltip(
+    usage_data: &AllUsageData,
+    usage_by_profile: &BTreeMap<String, AllUsageData>,
+    forecast: Option<&QuotaForecast>,
+) -> String {
+    let (token_pct, mcp_pct) = quota_percentages(usage_data);
+
+    // Show when the quota values were actually fetched, not when the refresh ran
+    let fetched_at = usage_data.status.quota.fetched_at.unwrap_or(usage_data.timestamp);
+    let time = chrono::DateTime::from_timestamp(fetched_at, 0)
+        .map(|date| date.with_timezone(&chrono::Local).format("%H:%M").to_string())
+        .unwrap_or_default();
+
+    let mut tooltip = match &usage_data.status.quota.error {
+        Some(error) => format!(
+            "Tokens: {:.1}% | MCP: {:.1}%\nStale since: {} ({})",
+            token_pct, mcp_pct, time, error.message
+        ),
+        None => format!("Tokens: {:.1}% | MCP: {:.1}%\nUpdated: {}", token_pct, mcp_pct, time),
+    };
+
+    if let Some(countdown) = reset_countdown(usage_data) {
+        tooltip.push_str(&format!("\n{}", countdown));
+    }
+
+    // Only warn when the quota runs out before it resets on its own
+    if let Some(exhausted_at) = forecast.filter(|f| f.exhausted_before_reset).and_then(|f| f.exhausted_at) {
+        let remaining = exhausted_at - chrono::Local::now().timestamp();
+        if remaining > 0 {
+            tooltip.push_str(&format!("\nAt this pace: exhausted in {}", forecast::format_duration(remaining)));
+        } else {
+            tooltip.push_str("\nToken quota exhausted");
+        }
+    }
+
+    if usage_by_profile.len() > 1 {
+        for (name, data) in usage_by_profile {
+            let (token_pct, mcp_pct) = quota_percentages(data);
+            tooltip.push_str(&format!("\n{}: T {:.0}% | M {:.0}%", name, token_pct, mcp_pct));
+        }
+    }
+
+    tooltip
+}
+
+/// Create dynamic menu with usage statistics and a profile switcher
+///
+/// The profile submenu is only shown when more than one profile is configured.
+fn create_tray_menu_with_stats<R: Runtime>(
+    app: &AppHandle<R>,
+    usage_data: Option<&AllUsageData>,
+    profile_names: &[String],
+    active_profile: &str,
+) -> Result<Menu<R>, tauri::Error> {
+    let menu = Menu::new(app)?;
+    let separator = PredefinedMenuItem::separator(app)?;
+
+    if let Some(usage_data) = usage_data {
+        let (token_pct, mcp_pct) = quota_percentages(usage_data);
+
+        // Create stats item
+        let stale_suffix = if usage_data.status.quota.error.is_some() { " (stale)" } else { "" };
+        let stats_text = format!("Tokens: {:.1}% | MCP: {:.1}%{}", token_pct, mcp_pct, stale_suffix);
+        let stats_item = MenuItem::with_id(app, "stats", stats_text, true, None::<&str>)?;
+        menu.append(&stats_item)?;
+
+        if let Some(countdown) = reset_countdown(usage_data) {
+            let reset_item = MenuItem::with_id(app, "reset", countdown, false, None::<&str>)?;
+            menu.append(&reset_item)?;
+        }
+        menu.append(&separator)?;
+    }
+
+    if profile_names.len() > 1 {
+        let profile_menu = Submenu::with_id(app, "profiles", format!("Profile: {}", active_profile), true)?;
+        for name in profile_names {
+            let item = CheckMenuItem::with_id(
+                app,
+                format!("profile:{}", name),
+                name,
+                true,
+                name == active_profile,
+                None::<&str>,
+            )?;
+            profile_menu.append(&item)?;
+        }
+        menu.append_items(&[&profile_menu, &separator])?;
+    }
+
+    // Create control items
+    let show_item = MenuItem::with_id(app, "show", "Show", true, None::<&str>)?;
+    let hide_item = MenuItem::with_id(app, "hide", "Hide", true, None::<&str>)?;
+    let refresh_item = MenuItem::with_id(app, "refresh", "Refresh Now", true, None::<&str>)?;
+    let quit_item = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
+
+    menu.append_items(&[
+        &show_item,
+        &hide_item,
+        &refresh_item,
+        &separator,
+        &quit_item,
+    ])?;
+
+    Ok(menu)
+}
+
+/// Bring the dashboard window to the front
+pub fn show_main_window<R: Runtime>(app: &AppHandle<R>) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+/// Update tray icon tooltip, title, and menu with latest usage data
+pub fn update_tray<R: Runtime>(app: &AppHandle<R>) -> Result<(), Box<dyn std::error::Error>> {
+    let state = app.state::<crate::commands::AppState>();
+    let config = state.config.lock().unwrap().clone();
+    let usage_by_profile = state.usage_by_profile.lock().unwrap().clone();
+    let active_error = state.errors_by_profile.lock().unwrap().get(&config.active_profile).cloned();
+    let active_forecast = state.forecasts_by_profile.lock().unwrap().get(&config.active_profile).cloned();
+    let tray_id_opt = state.tray_id.lock().unwrap().clone();
+
+    let Some(tray_id) = tray_id_opt else {
+        tracing::debug!("No tray ID stored");
+        return Ok(());
+    };
+    let Some(tray) = app.tray_by_id(&tray_id) else {
+        tracing::warn!(tray_id = ?tray_id, "No tray found");
+        return Ok(());
+    };
+
+    let active_data = usage_by_profile.get(&config.active_profile);
+
+    // Update menu with stats and the profile switcher
+    let profile_names: Vec<String> = config.all_profiles().into_iter().map(|p| p.name).collect();
+    let new_menu = create_tray_menu_with_stats(app, active_data, &profile_names, &config.active_profile)?;
+    tray.set_menu(Some(new_menu))?;
+
+    // Stale percentages are misleading when the token no longer works
+    if let Some(error) = active_error.filter(|e| e.is_auth_error()) {
+        let title = match error {
+            AppError::NotConfigured => "🆉 Setup required",
+            _ => "🆉 Auth expired",
+        };
+        tray.set_title(Some(title))?;
+        tray.set_icon(Some(tray_icon::quota_icon(None)))?;
+        tray.set_tooltip(Some(format!("{}\nOpen Settings to update your token", error).as_str()))?;
+        return Ok(());
+    }
+
+    if let Some(data) = active_data {
+        let others: Vec<&AllUsageData> = if config.tray_show_worst_case {
+            usage_by_profile.iter()
+                .filter(|(name, _)| **name != config.active_profile)
+                .map(|(_, data)| data)
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        // Update title; a template that no longer parses falls back to the default
+        let template = TrayTemplate::parse(&config.tray_title_template).unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "Invalid tray title template, using the default");
+            TrayTemplate::parse(crate::tray_title::DEFAULT_TEMPLATE).unwrap()
+        });
+        let title = generate_tray_title(&template, data, &others, active_forecast.as_ref());
+        tracing::trace!(title = %title, "Updating tray title");
+        tray.set_title(Some(title.as_str()))?;
+
+        // Update icon; trays that hide the title still show the ring
+        let (token_pct, _) = worst_case_percentages(data, &others);
+        tray.set_icon(Some(tray_icon::quota_icon(Some(token_pct))))?;
+
+        // Update tooltip
+        let tooltip = generate_tray_tooltip(data, &usage_by_profile, active_forecast.as_ref());
+        tray.set_tooltip(Some(tooltip.as_str()))?;
+    } else {
+        tracing::debug!("No usage data available");
+    }
+    Ok(())
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    logging::init();
+
+    tauri::Builder::default()
+        .plugin(tauri_plugin_opener::init())
+        .setup(|app| {
+            // Resolve config from defaults, config.json, ZAI_* variables and --flags
+            let args: Vec<String> = std::env::args().skip(1).collect();
+            // Invalid overrides are skipped inside; only an unreadable config.json fails here
+            let (config, config_layers) = match load_layered_config(&args) {
+                Ok(layered) => (layered.config, layered.layers),
+                Err(e) => {
+                    tracing::error!(error = %e, "Failed to load config");
+                    (Config::default(), ConfigLayers::default())
+                }
+            };
+            logging::configure(&config);
+            let refresh_interval_minutes = config.refresh_interval_minutes;
+            let metrics_listen_address = config.metrics_listen_address.clone();
+            let profile_names: Vec<String> = config.all_profiles().into_iter().map(|p| p.name).collect();
+            let active_profile = config.active_profile.clone();
+
+            // Drop expired history before the first refresh appends to it
+            let history = HistoryStore::open_default();
+            if let Err(e) = history.prune(config.history_retention_days) {
+                tracing::warn!(error = %e, "Failed to prune usage history");
+            }
+
+            // Set up app state with empty usage data initially
+            app.manage(AppState {
+                config: std::sync::Mutex::new(config),
+                config_layers: std::sync::Mutex::new(config_layers),
+                usage_by_profile: std::sync::Mutex::new(BTreeMap::new()),
+                errors_by_profile: std::sync::Mutex::new(BTreeMap::new()),
+                forecasts_by_profile: std::sync::Mutex::new(BTreeMap::new()),
+                time_window: std::sync::Mutex::new(TimeWindow::default()),
+                tray_id: std::sync::Mutex::new(None),
+                scheduler: Scheduler::new(),
+                history,
+                alerts: AlertEngine::open_default(),
+                webhook_dispatcher: WebhookDispatcher::open_default(),
+                notifications: NotificationCenter::new(Box::new(SystemNotifier)),
+                metrics: MetricsServer::new(),
+            });
+
+            // Create initial menu (will be updated when data arrives)
+            let menu = create_tray_menu_with_stats(app.handle(), None, &profile_names, &active_profile)
+                .unwrap();
+
+            // Build the tray icon
+            let tray = TrayIconBuilder::new()
+                .menu(&menu)
+                .show_menu_on_left_click(true)
+                .icon(tray_icon::quota_icon(None))
+                .title("🆉 Loading...")
+                .build(app)
+                .unwrap();
+
+            // Get the tray's ID and store it for later access
+            let tray_id = tray.id().clone();
+            tracing::debug!(tray_id = ?tray_id, "Tray created");
+
+            // Store the tray ID in app state
+            let state = app.state::<crate::commands::AppState>();
+            *state.tray_id.lock().unwrap() = Some(tray_id.clone());
+
+            // Hide window on startup (menubar app behavior)
+            if let Some(window) = app.get_webview_window("main") {
+                window.hide().unwrap();
+            }
+
+            // Start background polling; the first refresh runs immediately
+            state.scheduler.start(app.handle().clone(), refresh_interval_minutes);
+
+            // Expose usage to Prometheus if a listen address is configured
+            state.metrics.start(app.handle().clone(), metrics_listen_address);
+
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            commands::get_usage_data,
+            commands::save_config,
+            commands::get_config,
+            commands::resize_window,
+            commands::get_usage_history,
+            commands::get_quota_forecast,
+            commands::save_alert_rules,
+            commands::save_webhooks,
+            commands::test_webhook,
+            commands::get_webhook_deliveries,
+            commands::clear_usage_history,
+            commands::get_all_usage_data,
+            commands::save_profile,
+            commands::delete_profile,
+            commands::set_active_profile,
+            commands::get_config_sources,
+            commands::validate_base_url,
+            commands::export_diagnostics,
+        ])
+        .on_menu_event(|app, event| {
+            match event.id.0.as_str() {
+                "show" => show_main_window(app),
+                "hide" => {
+                    if let Some(window) = app.get_webview_window("main") {
+                        let _ = window.hide();
+                    }
+                }
+                "refresh" => {
+                    // Fetch directly; the result reaches the tray and webview via refresh_usage
+                    let app_handle = app.clone();
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = scheduler::refresh_usage(&app_handle, None).await {
+                            tracing::warn!(error = %e, "Manual refresh failed");
+                        }
+                    });
+                }
+                "quit" => {
+                    app.exit(0);
+                }
+                id if id.starts_with("profile:") => {
+                    let name = id.trim_start_matches("profile:");
+                    if let Err(e) = commands::activate_profile(app, name) {
+                        tracing::warn!(profile = %name, error = %e, "Failed to switch profile");
+                    }
+                }
+                _ => {}
+            }
+        })
+        .on_tray_icon_event(|app, event| {
+            if let TrayIconEvent::Click { .. } = event {
+                if let Some(window) = app.get_webview_window("main") {
+                    if window.is_visible().unwrap() {
+                        let _ = window.hide();
+                    } else {
+                        let _ = window.show();
+                        let _ = window.set_focus();
+                    }
+                }
+            }
+        })
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}
@@ -0,0 +1,314 @@
+//! Headless command-line client for the Z.ai usage APIs
+//!
+//! Shares the config file, secret store and API client with the tray app,
+//! so it works on machines without a desktop session.
+
+use serde::Serialize;
+use std::process::ExitCode;
+use zai_usage_monitor_lib::api::UsageClient;
+use zai_usage_monitor_lib::config::load_layered_config;
+use zai_usage_monitor_lib::error::AppError;
+use zai_usage_monitor_lib::labels;
+use zai_usage_monitor_lib::types::{ModelUsageItem, QuotaLimit, TimeWindow, ToolUsageItem, UsageStatus};
+
+const USAGE: &str = "\
+Usage: zai-usage <COMMAND> [OPTIONS]
+
+Commands:
+  quota    Show quota limits
+  models   Show token and request counts per model
+  tools    Show tool (MCP) call counts
+  all      Show everything
+
+Options:
+  --json              Print JSON instead of tables
+  --profile <NAME>    Query a named profile instead of the active one
+  --window <KIND>     last_24_hours (default), last_5_hours, today, last_7_days, last_30_days
+  --since <UNIX>      Start of a custom window (requires --until)
+  --until <UNIX>      End of a custom window (requires --since)
+  -h, --help          Print this help
+
+Any config field can also be overridden, e.g. --auth-token or --base-url,
+or through ZAI_<FIELD> environment variables.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Quota,
+    Models,
+    Tools,
+    All,
+}
+
+/// Parsed command line; arguments we don't recognize are left for the config layer
+struct Args {
+    command: Command,
+    json: bool,
+    profile: Option<String>,
+    window: TimeWindow,
+    config_args: Vec<String>,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    let json = args.iter().any(|a| a == "--json");
+    let result = parse_args(args).and_then(|args| {
+        let runtime = tokio::runtime::Runtime::new()
+            .map_err(|e| AppError::Io(format!("Failed to start runtime: {}", e)))?;
+        runtime.block_on(run(args))
+    });
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            if json {
+                eprintln!("{}", serde_json::to_string(&e).unwrap_or_else(|_| e.to_string()));
+            } else {
+                eprintln!("Error: {}", e);
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(args: Vec<String>) -> Result<Args, AppError> {
+    let mut command = None;
+    let mut json = false;
+    let mut profile = None;
+    let mut window_kind = None;
+    let mut since = None;
+    let mut until = None;
+    let mut config_args = Vec::new();
+
+    let mut iter = args.into_iter();
+    while let Some(arg) = iter.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+            _ => (arg.clone(), None),
+        };
+        let mut value = |name: &str| {
+            inline_value
+                .clone()
+                .or_else(|| iter.next())
+                .ok_or_else(|| AppError::InvalidInput(format!("{} needs a value", name)))
+        };
+
+        match flag.as_str() {
+            "--json" => json = true,
+            "--profile" => profile = Some(value("--profile")?),
+            "--window" => window_kind = Some(value("--window")?),
+            "--since" => since = Some(parse_timestamp("--since", &value("--since")?)?),
+            "--until" => until = Some(parse_timestamp("--until", &value("--until")?)?),
+            "quota" | "models" | "tools" | "all" if command.is_none() => {
+                command = Some(match flag.as_str() {
+                    "quota" => Command::Quota,
+                    "models" => Command::Models,
+                    "tools" => Command::Tools,
+                    _ => Command::All,
+                });
+            }
+            _ => config_args.push(arg),
+        }
+    }
+
+    let command = command.ok_or_else(|| {
+        AppError::InvalidInput("Expected a command: quota, models, tools or all".to_string())
+    })?;
+
+    let window = match (since, until, window_kind) {
+        (Some(start), Some(end), None) => TimeWindow::Custom { start, end },
+        (None, None, Some(kind)) => serde_json::from_value(serde_json::json!({ "kind": kind }))
+            .map_err(|_| AppError::InvalidInput(format!("Unknown window: {}", kind)))?,
+        (None, None, None) => TimeWindow::default(),
+        (Some(_), Some(_), Some(_)) => {
+            return Err(AppError::InvalidInput(
+                "--window cannot be combined with --since/--until".to_string(),
+            ))
+        }
+        _ => {
+            return Err(AppError::InvalidInput(
+                "--since and --until must be given together".to_string(),
+            ))
+        }
+    };
+
+    Ok(Args {
+        command,
+        json,
+        profile,
+        window,
+        config_args,
+    })
+}
+
+fn parse_timestamp(flag: &str, value: &str) -> Result<i64, AppError> {
+    value
+        .parse()
+        .map_err(|_| AppError::InvalidInput(format!("{} expects Unix seconds, got {}", flag, value)))
+}
+
+async fn run(args: Args) -> Result<(), AppError> {
+    let layered = load_layered_config(&args.config_args)?;
+    for warning in &layered.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    let config = layered.config;
+    let profile_name = args.profile.unwrap_or_else(|| config.active_profile.clone());
+    let profile = config
+        .find_profile(&profile_name)
+        .ok_or_else(|| AppError::InvalidInput(format!("Unknown profile: {}", profile_name)))?;
+
+    if profile.auth_token.is_empty() {
+        return Err(AppError::NotConfigured);
+    }
+
+    let client = UsageClient::new(config.for_profile(&profile));
+
+    match args.command {
+        Command::Quota => {
+            let limits = client.fetch_quota_limits().await?;
+            output(args.json, &limits, || print_quota(&limits))
+        }
+        Command::Models => {
+            // Without quota data we can still show the aggregate
+            let model_codes = client
+                .fetch_quota_limits()
+                .await
+                .map(|limits| UsageClient::model_codes(&limits))
+                .unwrap_or_default();
+            let models = client.fetch_model_usage(&args.window, &model_codes).await?;
+            output(args.json, &models, || print_models(&models.items))
+        }
+        Command::Tools => {
+            let tools = client.fetch_tool_usage(&args.window).await?;
+            output(args.json, &tools, || print_tools(&tools))
+        }
+        Command::All => {
+            let now = chrono::Local::now().timestamp();
+            let data = client
+                .fetch_all(&args.window)
+                .await
+                .into_usage_data(None, &profile.name, args.window, now)?;
+            output(args.json, &data, || {
+                print_quota(&data.quota_limits);
+                println!();
+                print_models(&data.model_usage);
+                println!();
+                print_tools(&data.tool_usage);
+                print_section_errors(&data.status);
+            })
+        }
+    }
+}
+
+/// Print `value` as JSON, or fall back to the human-readable table
+fn output<T: Serialize>(json: bool, value: &T, table: impl FnOnce()) -> Result<(), AppError> {
+    if json {
+        let text = serde_json::to_string_pretty(value)
+            .map_err(|e| AppError::Parse(format!("Failed to serialize output: {}", e)))?;
+        println!("{}", text);
+    } else {
+        table();
+    }
+    Ok(())
+}
+
+fn print_quota(limits: &[QuotaLimit]) {
+    let rows = limits
+        .iter()
+        .map(|limit| {
+            vec![
+                labels::quota_label(limit),
+                format!("{:.1}%", limit.percentage),
+                optional(limit.current_value),
+                optional(limit.usage),
+                optional(limit.remaining),
+                limit
+                    .resets_at
+                    .and_then(|at| chrono::DateTime::from_timestamp(at, 0))
+                    .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+            ]
+        })
+        .collect();
+
+    print_table(&["QUOTA", "USED", "CURRENT", "LIMIT", "REMAINING", "RESETS"], rows);
+}
+
+fn print_models(items: &[ModelUsageItem]) {
+    let rows = items
+        .iter()
+        .map(|item| {
+            vec![
+                item.model.clone(),
+                item.token_count.to_string(),
+                item.request_count.to_string(),
+            ]
+        })
+        .collect();
+
+    print_table(&["MODEL", "TOKENS", "REQUESTS"], rows);
+}
+
+fn print_tools(items: &[ToolUsageItem]) {
+    let rows = items
+        .iter()
+        .map(|item| vec![item.tool_name.clone(), item.usage_count.to_string()])
+        .collect();
+
+    print_table(&["TOOL", "CALLS"], rows);
+}
+
+fn print_section_errors(status: &UsageStatus) {
+    for (name, section) in [("quota", &status.quota), ("models", &status.models), ("tools", &status.tools)] {
+        if let Some(error) = &section.error {
+            eprintln!("Warning: failed to fetch {}: {}", name, error.message);
+        }
+    }
+}
+
+fn optional(value: Option<i64>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_else(|| "-".to_string())
+}
+
+/// Print rows with columns padded to the widest cell; numbers are right-aligned
+fn print_table(headers: &[&str], rows: Vec<Vec<String>>) {
+    if rows.is_empty() {
+        println!("No {} data", headers[0].to_lowercase());
+        return;
+    }
+
+    let widths: Vec<usize> = (0..headers.len())
+        .map(|i| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .chain(std::iter::once(headers[i].len()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let format_row = |cells: Vec<String>| {
+        cells
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| {
+                if i == 0 {
+                    format!("{:<width$}", cell, width = widths[i])
+                } else {
+                    format!("{:>width$}", cell, width = widths[i])
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("  ")
+    };
+
+    println!("{}", format_row(headers.iter().map(|h| h.to_string()).collect()));
+    for row in rows {
+        println!("{}", format_row(row));
+    }
+}
@@ -1,106 +1,461 @@
+use crate::alerts::AlertEngine;
 use crate::api::UsageClient;
-use crate::types::{AllUsageData, Config};
+use crate::config::{ConfigLayers, ConfigValueSource};
+use crate::error::AppError;
+use crate::history::HistoryStore;
+use crate::metrics::MetricsServer;
+use crate::notifications::NotificationCenter;
+use crate::scheduler::Scheduler;
+use crate::webhooks::WebhookDispatcher;
+use crate::types::{
+    AlertMetric, AlertRule, AlertSeverity, AllUsageData, BaseUrlValidation, Config, Profile,
+    QuotaForecast, QuotaKind, RetryPolicy, TimeWindow, UsageAlert, WebhookConfig, WebhookDelivery, DEFAULT_PROFILE,
+};
+use std::collections::BTreeMap;
 use std::sync::Mutex;
-use tauri::{State, AppHandle, Emitter, Manager};
-use serde_json::json;
+use tauri::{State, AppHandle, Emitter, Manager, Runtime};
 use tauri::tray::TrayIconId;
 
-/// Application state for holding the config, last usage data, and tray ID
+/// Application state for holding the config, per-profile usage data and errors, tray ID,
+/// refresh scheduler, history, alert rule state, notification and webhook delivery and
+/// metrics endpoint
 pub struct AppState {
+    /// Effective config: config.json with environment and command-line overrides applied
     pub config: Mutex<Config>,
-    pub last_usage_data: Mutex<Option<AllUsageData>>,
+    /// The layers `config` was resolved from; edits go to the file layer
+    pub config_layers: Mutex<ConfigLayers>,
+    /// Latest usage data keyed by profile name
+    pub usage_by_profile: Mutex<BTreeMap<String, AllUsageData>>,
+    /// Error from the most recent refresh of each profile, cleared on success
+    pub errors_by_profile: Mutex<BTreeMap<String, AppError>>,
+    /// Token quota forecast from the most recent refresh of each profile
+    pub forecasts_by_profile: Mutex<BTreeMap<String, QuotaForecast>>,
+    /// Model/tool usage range last selected in the webview, reused by every refresh
+    pub time_window: Mutex<TimeWindow>,
     pub tray_id: Mutex<Option<TrayIconId>>,
+    pub scheduler: Scheduler,
+    pub history: HistoryStore,
+    pub alerts: AlertEngine,
+    pub webhook_dispatcher: WebhookDispatcher,
+    pub notifications: NotificationCenter,
+    pub metrics: MetricsServer,
+}
+
+impl AppState {
+    /// Latest usage data of the active profile
+    pub fn active_usage_data(&self) -> Option<AllUsageData> {
+        let active = self.config.lock().unwrap().active_profile.clone();
+        self.usage_by_profile.lock().unwrap().get(&active).cloned()
+    }
+
+    /// Apply `edit` to the file layer, save it and return the new effective config
+    ///
+    /// Environment and command-line overrides stay in effect but are never
+    /// written to config.json or the secret store.
+    pub fn update_config(
+        &self,
+        edit: impl FnOnce(&mut Config) -> Result<(), AppError>,
+    ) -> Result<Config, AppError> {
+        let mut layers = self.config_layers.lock().unwrap();
+        let mut file = layers.file().clone();
+        edit(&mut file)?;
+        let file = layers.without_overrides(&file)?;
+
+        // Keep the secret references the save filled in, so the next save can skip unchanged tokens
+        let file = crate::config::save_config(&file, layers.file())?;
+        let effective = layers.apply(&file)?;
+        layers.set_file(file)?;
+
+        // Keep the log level and the secrets redacted from logs in step with the edit
+        crate::logging::configure(&effective);
+        *self.config.lock().unwrap() = effective.clone();
+        Ok(effective)
+    }
 }
 
 /// Resize the window to fit content
 #[tauri::command]
-pub async fn resize_window(app: AppHandle, width: i32, height: i32) -> Result<(), String> {
+pub async fn resize_window(app: AppHandle, width: i32, height: i32) -> Result<(), AppError> {
     if let Some(window) = app.get_webview_window("main") {
         let width = width as u32;
         let height = height as u32;
         window.set_size(tauri::Size::Physical(tauri::PhysicalSize { width, height }))
-            .map_err(|e| format!("Failed to resize window: {}", e))?;
+            .map_err(|e| AppError::Tauri(format!("Failed to resize window: {}", e)))?;
     }
     Ok(())
 }
 
 /// Fetch all usage data from the API
+///
+/// `window` selects the model/tool usage range and is kept for later scheduled
+/// refreshes; left out, the previously selected range (initially the last 24 hours) is used.
 #[tauri::command]
 pub async fn get_usage_data(
-    state: State<'_, AppState>,
+    window: Option<TimeWindow>,
     app: AppHandle,
-) -> Result<AllUsageData, String> {
-    let config = state.config.lock().unwrap().clone();
-
-    let client = UsageClient::new(config);
-
-    // Fetch all data in parallel for better performance
-    let (model_usage_result, tool_usage, quota_limits) = tokio::try_join!(
-        client.fetch_model_usage(),
-        client.fetch_tool_usage(),
-        client.fetch_quota_limits()
-    )
-    .map_err(|e| format!("Failed to fetch data: {}", e))?;
-
-    let data = AllUsageData {
-        model_usage: model_usage_result.items,
-        model_usage_timeseries: model_usage_result.timeseries,
-        tool_usage,
-        quota_limits: quota_limits.clone(),
-        timestamp: chrono::Local::now().timestamp(),
-    };
-
-    // Store in state for tray access
-    *state.last_usage_data.lock().unwrap() = Some(data.clone());
-
-    // Emit event to trigger tray update (lib.rs listens for this)
-    let _ = app.emit("usage-data-updated", ());
-
-    // Emit usage alerts for high usage
-    for limit in &quota_limits {
-        if limit.percentage >= 90.0 {
-            let _ = app.emit("usage-alert", json!({
-                "type": limit.type_field,
-                "percentage": limit.percentage,
-                "severity": "critical"
-            }));
-        } else if limit.percentage >= 70.0 {
-            let _ = app.emit("usage-alert", json!({
-                "type": limit.type_field,
-                "percentage": limit.percentage,
-                "severity": "warning"
-            }));
-        }
-    }
-
-    Ok(data)
+) -> Result<AllUsageData, AppError> {
+    crate::scheduler::refresh_usage(&app, window).await
 }
 
 /// Save configuration to persistent storage
+///
+/// Optional arguments left out by the caller keep their current values.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub fn save_config(
     auth_token: String,
     base_url: String,
     refresh_interval_minutes: u64,
+    history_retention_days: Option<u64>,
+    retry_policy: Option<RetryPolicy>,
+    metrics_listen_address: Option<String>,
+    tray_title_template: Option<String>,
+    monitor_base_url: Option<String>,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    let new_config = Config {
-        auth_token,
-        base_url,
-        refresh_interval_minutes,
-    };
+    app: AppHandle,
+) -> Result<(), AppError> {
+    // Reject a broken template or URL before anything is saved
+    if let Some(template) = &tray_title_template {
+        crate::tray_title::TrayTemplate::parse(template)?;
+    }
+    UsageClient::base_domain(&base_url)?;
 
-    // Save to persistent storage
-    crate::config::save_config(&new_config)?;
+    // Start from the saved config so settings not sent by the caller are preserved
+    let new_config = state.update_config(|file| {
+        file.auth_token = auth_token;
+        file.base_url = base_url;
+        file.refresh_interval_minutes = refresh_interval_minutes;
+        if let Some(days) = history_retention_days {
+            file.history_retention_days = days;
+        }
+        if let Some(policy) = retry_policy {
+            file.retry = policy;
+        }
+        // An empty address disables the metrics endpoint
+        if let Some(address) = metrics_listen_address {
+            file.metrics_listen_address = Some(address).filter(|a| !a.trim().is_empty());
+        }
+        if let Some(template) = tray_title_template {
+            file.tray_title_template = template;
+        }
+        // An empty monitor URL goes back to deriving it from the base URL
+        if let Some(url) = monitor_base_url {
+            file.monitor_base_url = Some(url).filter(|u| !u.trim().is_empty());
+            UsageClient::monitor_root(file)?;
+        }
+        Ok(())
+    })?;
+
+    // Overrides still win, so act on the effective values
+    let refresh_interval_minutes = new_config.refresh_interval_minutes;
+    let retention_days = new_config.history_retention_days;
+    let metrics_address = new_config.metrics_listen_address.clone();
 
-    // Update in-memory state
-    *state.config.lock().unwrap() = new_config;
+    // Pick up a changed refresh interval without requiring a restart
+    state.scheduler.restart_if_changed(app.clone(), refresh_interval_minutes);
+    state.metrics.restart_if_changed(app.clone(), metrics_address);
+
+    // Apply a shortened retention period right away
+    state.history.prune(retention_days)?;
+
+    // Show a changed title template without waiting for the next refresh
+    if let Err(e) = crate::update_tray(&app) {
+        tracing::warn!(error = %e, "Failed to update tray");
+    }
 
     Ok(())
 }
 
+/// Replace the alert rules
+///
+/// State of removed rules is dropped; rules that keep their id keep their state.
+#[tauri::command]
+pub fn save_alert_rules(rules: Vec<AlertRule>, state: State<'_, AppState>) -> Result<(), AppError> {
+    crate::alerts::validate_rules(&rules)?;
+
+    let new_config = state.update_config(|file| {
+        file.alert_rules = rules;
+        Ok(())
+    })?;
+
+    state.alerts.retain_rules(&new_config.alert_rules)
+
+}
+
+/// Replace the alert webhooks
+#[tauri::command]
+pub fn save_webhooks(webhooks: Vec<WebhookConfig>, state: State<'_, AppState>) -> Result<(), AppError> {
+    crate::webhooks::validate_webhooks(&webhooks)?;
+
+    state.update_config(|file| {
+        file.webhooks = webhooks;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+/// Probe the monitor endpoints for a base URL and report which ones work
+///
+/// Arguments left out fall back to the active profile's settings, so URLs can
+/// be checked before they are saved. Each endpoint is tried once.
+#[tauri::command]
+pub async fn validate_base_url(
+    base_url: Option<String>,
+    monitor_base_url: Option<String>,
+    auth_token: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<BaseUrlValidation, AppError> {
+    let mut config = {
+        let config = state.config.lock().unwrap();
+        config
+            .find_profile(&config.active_profile)
+            .map(|profile| config.for_profile(&profile))
+            .unwrap_or_else(|| config.clone())
+    };
+    if let Some(base_url) = base_url {
+        config.base_url = base_url;
+    }
+    if let Some(url) = monitor_base_url {
+        config.monitor_base_url = Some(url).filter(|u| !u.trim().is_empty());
+    }
+    if let Some(token) = auth_token.filter(|t| !t.is_empty()) {
+        config.auth_token = token;
+    }
+    config.retry.max_attempts = 1;
+
+    UsageClient::new(config)
+        .with_timeout(std::time::Duration::from_secs(10))?
+        .probe_endpoints()
+        .await
+}
+
+/// Send a sample alert to one webhook and return the outcome
+#[tauri::command]
+pub async fn test_webhook(id: String, state: State<'_, AppState>) -> Result<WebhookDelivery, AppError> {
+    let (hook, policy, profile) = {
+        let config = state.config.lock().unwrap();
+        let hook = config
+            .webhooks
+            .iter()
+            .find(|hook| hook.id == id)
+            .cloned()
+            .ok_or_else(|| AppError::InvalidInput(format!("Unknown webhook: {}", id)))?;
+        (hook, config.retry.clone(), config.active_profile.clone())
+    };
+
+    let alert = UsageAlert {
+        profile,
+        rule_id: "test".to_string(),
+        quota: QuotaKind::Tokens,
+        label: "Token usage (5 Hour)".to_string(),
+        metric: AlertMetric::Percentage,
+        value: 75.0,
+        threshold: 70.0,
+        percentage: 75.0,
+        severity: AlertSeverity::Warning,
+        timestamp: chrono::Local::now().timestamp(),
+    };
+    Ok(state.webhook_dispatcher.deliver(&hook, &alert, &policy).await)
+}
+
+/// Most recent webhook deliveries, newest first
+#[tauri::command]
+pub fn get_webhook_deliveries(
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<WebhookDelivery>, AppError> {
+    state.webhook_dispatcher.deliveries(limit.unwrap_or(100))
+}
+
+/// Write a sanitized diagnostic bundle for bug reports and return its path
+///
+/// The bundle holds the app version and platform, the config, the section
+/// status, errors and forecast of each profile, recent webhook deliveries and
+/// the latest log lines, with tokens, secrets and personal fields redacted.
+#[tauri::command]
+pub fn export_diagnostics(state: State<'_, AppState>) -> Result<String, AppError> {
+    let config = state.config.lock().unwrap().clone();
+    let status: BTreeMap<String, serde_json::Value> = state
+        .usage_by_profile
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(name, data)| (name.clone(), serde_json::json!({ "timestamp": data.timestamp, "status": data.status })))
+        .collect();
+
+    let bundle = serde_json::json!({
+        "app_version": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "config": config,
+        "profiles": status,
+        "errors": *state.errors_by_profile.lock().unwrap(),
+        "forecasts": *state.forecasts_by_profile.lock().unwrap(),
+        "webhook_deliveries": state.webhook_dispatcher.deliveries(50)?,
+    });
+
+    let path = crate::logging::write_diagnostics(bundle)?;
+    Ok(path.display().to_string())
+}
+
 /// Load configuration from persistent storage
 #[tauri::command]
-pub fn get_config(state: State<'_, AppState>) -> Result<Config, String> {
+pub fn get_config(state: State<'_, AppState>) -> Result<Config, AppError> {
     Ok(state.config.lock().unwrap().clone())
 }
+
+/// Query stored usage snapshots within a time range (Unix seconds, inclusive)
+///
+/// `profile` limits the result to one profile; omit it to get all profiles.
+#[tauri::command]
+pub fn get_usage_history(
+    start: Option<i64>,
+    end: Option<i64>,
+    profile: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<AllUsageData>, AppError> {
+    state.history.query(start, end, profile.as_deref())
+}
+
+/// Burn rate and projected exhaustion of the 5-hour token quota
+///
+/// Defaults to the active profile. Returns `None` until a refresh has produced
+/// enough data to estimate the burn rate.
+#[tauri::command]
+pub fn get_quota_forecast(
+    profile: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Option<QuotaForecast>, AppError> {
+    let profile = profile.unwrap_or_else(|| state.config.lock().unwrap().active_profile.clone());
+    Ok(state.forecasts_by_profile.lock().unwrap().get(&profile).cloned())
+}
+
+/// Delete all stored usage history
+#[tauri::command]
+pub fn clear_usage_history(state: State<'_, AppState>) -> Result<(), AppError> {
+    state.history.clear()
+}
+
+/// Latest usage data of every profile, keyed by profile name
+#[tauri::command]
+pub fn get_all_usage_data(state: State<'_, AppState>) -> Result<BTreeMap<String, AllUsageData>, AppError> {
+    Ok(state.usage_by_profile.lock().unwrap().clone())
+}
+
+/// Add a named profile, or update it if the name already exists
+///
+/// Saving the "default" profile updates the top-level token and base URL.
+/// The profile's usage is then fetched in the background.
+#[tauri::command]
+pub fn save_profile(profile: Profile, state: State<'_, AppState>, app: AppHandle) -> Result<(), AppError> {
+    let name = profile.name.trim().to_string();
+    if name.is_empty() {
+        return Err(AppError::InvalidInput("Profile name cannot be empty".to_string()));
+    }
+
+    state.update_config(|file| {
+        if name == DEFAULT_PROFILE {
+            file.auth_token = profile.auth_token;
+            file.base_url = profile.base_url;
+            file.monitor_base_url = profile.monitor_base_url;
+        } else {
+            let profile = Profile { name: name.clone(), ..profile };
+            match file.profiles.iter_mut().find(|p| p.name == name) {
+                Some(existing) => {
+                    // Keep pointing at the stored token unless a new one was supplied
+                    let auth_token_ref = profile.auth_token_ref.clone().or(existing.auth_token_ref.take());
+                    *existing = Profile { auth_token_ref, ..profile };
+                }
+                None => file.profiles.push(profile),
+            }
+        }
+        Ok(())
+    })?;
+
+    // List the profile in the tray menu now and fetch its usage without waiting for the scheduler
+    if let Err(e) = crate::update_tray(&app) {
+        tracing::warn!(error = %e, "Failed to update tray");
+    }
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = crate::scheduler::refresh_single_profile(&app, &name).await {
+            tracing::warn!(profile = %name, error = %e, "Failed to refresh saved profile");
+        }
+    });
+
+    Ok(())
+}
+
+/// Remove a named profile; the default profile cannot be deleted
+#[tauri::command]
+pub fn delete_profile(name: String, state: State<'_, AppState>, app: AppHandle) -> Result<(), AppError> {
+    if name == DEFAULT_PROFILE {
+        return Err(AppError::InvalidInput("The default profile cannot be deleted".to_string()));
+    }
+
+    // The file layer holds the references filled in when the profile was saved
+    let mut token_ref = None;
+    state.update_config(|file| {
+        token_ref = file.profiles.iter()
+            .find(|p| p.name == name)
+            .and_then(|p| p.auth_token_ref.clone());
+        file.profiles.retain(|p| p.name != name);
+        if file.active_profile == name {
+            file.active_profile = DEFAULT_PROFILE.to_string();
+        }
+        Ok(())
+    })?;
+    state.usage_by_profile.lock().unwrap().remove(&name);
+    state.errors_by_profile.lock().unwrap().remove(&name);
+    state.forecasts_by_profile.lock().unwrap().remove(&name);
+
+    // Don't leave the deleted profile's token behind in the secret store
+    if let Some(token_ref) = token_ref {
+        if let Err(e) = crate::secrets::delete_reference(&token_ref) {
+            tracing::warn!(profile = %name, error = %e, "Failed to delete token");
+        }
+    }
+
+    if let Err(e) = crate::update_tray(&app) {
+        tracing::warn!(error = %e, "Failed to update tray");
+    }
+    Ok(())
+}
+
+/// Switch the profile shown in the tray and webview
+#[tauri::command]
+pub fn set_active_profile(name: String, app: AppHandle) -> Result<(), AppError> {
+    activate_profile(&app, &name)
+}
+
+/// Make `name` the active profile, persist it and refresh the tray and webview
+///
+/// Shared by the `set_active_profile` command and the tray profile submenu.
+pub fn activate_profile<R: Runtime>(app: &AppHandle<R>, name: &str) -> Result<(), AppError> {
+    let state = app.state::<AppState>();
+
+    if state.config.lock().unwrap().find_profile(name).is_none() {
+        return Err(AppError::InvalidInput(format!("Unknown profile: {}", name)));
+    }
+    state.update_config(|file| {
+        file.active_profile = name.to_string();
+        Ok(())
+    })?;
+
+    if let Err(e) = crate::update_tray(app) {
+        tracing::warn!(error = %e, "Failed to update tray");
+    }
+
+    // Show the already fetched data of the new profile right away
+    if let Some(data) = state.active_usage_data() {
+        let _ = app.emit("usage-data-updated", &data);
+    }
+    Ok(())
+}
+
+/// Report which layer (default, file, environment, command line) each config value came from
+///
+/// Describes the layers the running app resolved at startup, including saves since then.
+#[tauri::command]
+pub fn get_config_sources(state: State<'_, AppState>) -> Result<Vec<ConfigValueSource>, AppError> {
+    state.config_layers.lock().unwrap().sources()
+}
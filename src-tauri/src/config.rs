@@ -1,9 +1,121 @@
-use crate::types::Config;
+use crate::error::AppError;
+use crate::secrets;
+use crate::types::{Config, Profile, DEFAULT_PROFILE};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::PathBuf;
 
-/// Get the configuration file path for the current platform
-fn get_config_path() -> PathBuf {
+/// Prefix for environment variable overrides, e.g. `ZAI_REFRESH_INTERVAL_MINUTES`
+const ENV_PREFIX: &str = "ZAI_";
+
+/// Layer an effective config value was taken from, lowest precedence first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigSource {
+    Default,
+    File,
+    Environment,
+    CommandLine,
+}
+
+/// Effective value of one config field and the layer it came from
+///
+/// Tokens in `value` are masked.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigValueSource {
+    pub field: String,
+    pub source: ConfigSource,
+    pub value: Value,
+}
+
+/// Config resolved from every layer
+pub struct LayeredConfig {
+    /// Effective config: the file layer with every override applied
+    pub config: Config,
+    pub layers: ConfigLayers,
+    /// Overrides that were skipped because their value was invalid
+    pub warnings: Vec<String>,
+}
+
+/// The config.json layer and the environment/command-line overrides applied on top of it
+///
+/// Edits are made to the file layer so overrides are never written to config.json.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigLayers {
+    file: Config,
+    /// Top-level fields present in config.json
+    file_fields: Vec<String>,
+    /// Override values by field, with the layer each came from
+    overrides: BTreeMap<String, (Value, ConfigSource)>,
+}
+
+impl ConfigLayers {
+    /// Config as stored in config.json, with tokens resolved from the secret store
+    pub fn file(&self) -> &Config {
+        &self.file
+    }
+
+    /// The effective config for a file layer of `file`
+    pub fn apply(&self, file: &Config) -> Result<Config, AppError> {
+        let mut merged = to_object(file)?;
+        for (field, (value, _)) in &self.overrides {
+            merged.insert(field.clone(), value.clone());
+        }
+        serde_json::from_value(Value::Object(merged))
+            .map_err(|e| AppError::Config(format!("Invalid configuration: {}", e)))
+    }
+
+    /// Undo edits to overridden fields that only echo the override value back
+    ///
+    /// The webview edits the effective config, so a save would otherwise copy
+    /// e.g. `ZAI_AUTH_TOKEN` into config.json and the secret store.
+    pub fn without_overrides(&self, edited: &Config) -> Result<Config, AppError> {
+        let original = to_object(&self.file)?;
+        let mut edited = to_object(edited)?;
+        for (field, (value, _)) in &self.overrides {
+            if edited.get(field) == Some(value) {
+                match original.get(field) {
+                    Some(original) => edited.insert(field.clone(), original.clone()),
+                    None => edited.remove(field),
+                };
+            }
+        }
+        serde_json::from_value(Value::Object(edited))
+            .map_err(|e| AppError::Config(format!("Invalid configuration: {}", e)))
+    }
+
+    /// Replace the file layer after it has been saved
+    pub fn set_file(&mut self, file: Config) -> Result<(), AppError> {
+        self.file_fields = to_object(&file)?.keys().cloned().collect();
+        self.file = file;
+        Ok(())
+    }
+
+    /// Which layer each effective value came from
+    pub fn sources(&self) -> Result<Vec<ConfigValueSource>, AppError> {
+        let effective = to_object(&self.apply(&self.file)?)?;
+        Ok(overridable_fields()?
+            .into_iter()
+            .map(|field| {
+                // A token kept in the secret store still counts as coming from the file
+                let in_file = self.file_fields.contains(&field)
+                    || (field == "auth_token" && self.file_fields.iter().any(|f| f == "auth_token_ref"));
+                let source = match self.overrides.get(&field) {
+                    Some((_, source)) => *source,
+                    None if in_file => ConfigSource::File,
+                    None => ConfigSource::Default,
+                };
+                let value = mask_tokens(&field, effective.get(&field).cloned().unwrap_or(Value::Null));
+                ConfigValueSource { field, source, value }
+            })
+            .collect())
+    }
+}
+
+/// Get the app data directory holding config.json and usage history
+pub fn get_data_dir() -> PathBuf {
     // Use the appropriate config directory for each platform
     #[cfg(target_os = "macos")]
     let base_dir = dirs::config_dir()
@@ -13,41 +125,345 @@ fn get_config_path() -> PathBuf {
     let base_dir = dirs::config_dir()
         .unwrap_or_else(|| PathBuf::from("."));
 
-    base_dir
-        .join("zai-usage-monitor")
-        .join("config.json")
+    base_dir.join("zai-usage-monitor")
+}
+
+/// Get the configuration file path for the current platform
+fn get_config_path() -> PathBuf {
+    get_data_dir().join("config.json")
 }
 
 /// Load configuration from disk, or return default if not found
-pub fn load_config() -> Result<Config, String> {
+///
+/// Tokens are read from the secret store. Plaintext tokens left by older
+/// versions are moved into the store and config.json is rewritten without them.
+pub fn load_config() -> Result<Config, AppError> {
     let path = get_config_path();
 
     if path.exists() {
         let contents = fs::read_to_string(&path)
-            .map_err(|e| format!("Failed to read config: {}", e))?;
+            .map_err(|e| AppError::Config(format!("Failed to read config: {}", e)))?;
 
-        serde_json::from_str(&contents)
-            .map_err(|e| format!("Failed to parse config: {}", e))
+        let mut config: Config = serde_json::from_str(&contents)
+            .map_err(|e| AppError::Config(format!("Failed to parse config: {}", e)))?;
+
+        if resolve_secrets(&mut config) {
+            // Keep the plaintext token in memory if the store is unavailable
+            match save_config(&config, &Config::default()) {
+                Ok(saved) => config = saved,
+                Err(e) => tracing::warn!(error = %e, "Failed to migrate auth tokens to the secret store"),
+            }
+        }
+
+        Ok(config)
     } else {
         Ok(Config::default())
     }
 }
 
-/// Save configuration to disk
-pub fn save_config(config: &Config) -> Result<(), String> {
+/// Save configuration to disk, moving auth tokens into the secret store
+///
+/// `previous` is the config as last saved; tokens it already stored under the
+/// same reference are not written again. Returns `config` with every secret
+/// reference filled in and the tokens kept, to be used as the new `previous`.
+pub fn save_config(config: &Config, previous: &Config) -> Result<Config, AppError> {
     let path = get_config_path();
 
+    let mut saved = config.clone();
+    store_secrets(&mut saved, previous)?;
+
+    let mut config = saved.clone();
+    for (_, token, _) in token_slots(&mut config) {
+        token.clear();
+    }
+
     // Create parent directory if it doesn't exist
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+            .map_err(|e| AppError::Config(format!("Failed to create config directory: {}", e)))?;
     }
 
-    let contents = serde_json::to_string_pretty(config)
-        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+    let contents = serde_json::to_string_pretty(&config)
+        .map_err(|e| AppError::Config(format!("Failed to serialize config: {}", e)))?;
 
     fs::write(&path, contents)
-        .map_err(|e| format!("Failed to write config: {}", e))?;
+        .map_err(|e| AppError::Config(format!("Failed to write config: {}", e)))?;
+
+    Ok(saved)
+}
+
+/// Fill in tokens from their secret references
+///
+/// Returns true if any plaintext token was found and needs migrating.
+fn resolve_secrets(config: &mut Config) -> bool {
+    let mut has_plaintext = false;
+
+    for (name, token, reference) in token_slots(config) {
+        if !token.is_empty() {
+            has_plaintext = true;
+        } else if let Some(reference) = reference {
+            match secrets::resolve_reference(reference) {
+                Ok(Some(secret)) => *token = secret,
+                Ok(None) => tracing::warn!(profile = %name, "No stored token"),
+                Err(e) => tracing::warn!(profile = %name, error = %e, "Failed to read token"),
+            }
+        }
+    }
+
+    has_plaintext
+}
+
+/// Write every new or changed token to the configured secret store and point its reference there
+///
+/// Slots with an empty token keep their existing reference, so a store that
+/// was unavailable at load time never causes a token to be forgotten.
+fn store_secrets(config: &mut Config, previous: &Config) -> Result<(), AppError> {
+    let backend = config.secret_backend;
+    let stored = previous.all_profiles();
+    let mut store = None;
+
+    for (name, token, reference) in token_slots(config) {
+        if token.is_empty() {
+            continue;
+        }
+
+        // Each store write is a keychain call or an Argon2 key derivation
+        let key = format!("profile:{}", name);
+        let new_reference = backend.reference(&key);
+        let unchanged = stored.iter().any(|p| {
+            p.name == name && p.auth_token == *token && p.auth_token_ref.as_deref() == Some(new_reference.as_str())
+        });
+        if unchanged {
+            *reference = Some(new_reference);
+            continue;
+        }
+
+        // Only open the store when there is something to write
+        let store = match &mut store {
+            Some(store) => store,
+            None => store.insert(backend.open()?),
+        };
+
+        store.set(&key, token)?;
+
+        // Drop the copy left in a previously used backend
+        if let Some(old) = reference.replace(new_reference.clone()) {
+            if old != new_reference {
+                if let Err(e) = secrets::delete_reference(&old) {
+                    tracing::warn!(profile = %name, error = %e, "Failed to remove old token");
+                }
+            }
+        }
+    }
 
     Ok(())
 }
+
+/// Profile name, token and secret reference of the default profile and every named profile
+fn token_slots(config: &mut Config) -> impl Iterator<Item = (String, &mut String, &mut Option<String>)> {
+    std::iter::once((
+        DEFAULT_PROFILE.to_string(),
+        &mut config.auth_token,
+        &mut config.auth_token_ref,
+    ))
+    .chain(
+        config
+            .profiles
+            .iter_mut()
+            .map(|p| (p.name.clone(), &mut p.auth_token, &mut p.auth_token_ref)),
+    )
+}
+
+impl Config {
+    /// All profiles, starting with the implicit default built from the top-level fields
+    ///
+    /// The default profile is omitted when it has no token and named profiles exist,
+    /// so users who only use named profiles don't get a permanently failing entry.
+    pub fn all_profiles(&self) -> Vec<Profile> {
+        let mut profiles = Vec::with_capacity(self.profiles.len() + 1);
+
+        if !self.auth_token.is_empty() || self.profiles.is_empty() {
+            profiles.push(Profile {
+                name: DEFAULT_PROFILE.to_string(),
+                auth_token: self.auth_token.clone(),
+                auth_token_ref: self.auth_token_ref.clone(),
+                base_url: self.base_url.clone(),
+                monitor_base_url: self.monitor_base_url.clone(),
+            });
+        }
+
+        profiles.extend(self.profiles.iter().cloned());
+        profiles
+    }
+
+    /// Look up a profile by name, including the implicit default
+    pub fn find_profile(&self, name: &str) -> Option<Profile> {
+        self.all_profiles().into_iter().find(|p| p.name == name)
+    }
+
+    /// Config for querying a single profile with `UsageClient`
+    pub fn for_profile(&self, profile: &Profile) -> Config {
+        Config {
+            auth_token: profile.auth_token.clone(),
+            base_url: profile.base_url.clone(),
+            monitor_base_url: profile.monitor_base_url.clone(),
+            profiles: Vec::new(),
+            active_profile: profile.name.clone(),
+            ..self.clone()
+        }
+    }
+}
+
+/// Resolve the effective config: defaults < config file < environment < command line
+///
+/// Every top-level `Config` field can be overridden with `ZAI_<FIELD>` in the
+/// environment (e.g. `ZAI_AUTH_TOKEN`, `ZAI_REFRESH_INTERVAL_MINUTES`) or
+/// `--<field>` / `--<field>=<value>` on the command line (e.g. `--base-url`).
+/// Non-string fields take JSON values (`--retry '{"max_attempts": 5}'`).
+/// Unrecognized arguments are ignored, and an override with an invalid value
+/// is skipped with a warning rather than discarding the file config.
+pub fn load_layered_config(args: &[String]) -> Result<LayeredConfig, AppError> {
+    let mut layers = ConfigLayers {
+        file_fields: read_file_fields()?,
+        file: load_config()?,
+        overrides: BTreeMap::new(),
+    };
+    let fields = overridable_fields()?;
+
+    let env_overrides = fields.iter().filter_map(|field| {
+        let var = format!("{}{}", ENV_PREFIX, field.to_uppercase());
+        let raw = std::env::var(&var).ok()?;
+        Some((field.clone(), raw, ConfigSource::Environment, var))
+    });
+    let arg_overrides = parse_args(args, &fields).into_iter().map(|(field, raw)| {
+        let flag = format!("--{}", field.replace('_', "-"));
+        (field, raw, ConfigSource::CommandLine, flag)
+    });
+    let candidates: Vec<_> = env_overrides.chain(arg_overrides).collect();
+
+    let mut config = layers.apply(&layers.file)?;
+    let mut warnings = Vec::new();
+    for (field, raw, source, name) in candidates {
+        let current = to_object(&config)?;
+        let previous = layers.overrides.get(&field).cloned();
+
+        // Keep an override only if the config still deserializes with it
+        let result = parse_override(&raw, current.get(&field))
+            .map_err(|e| AppError::Config(e.to_string()))
+            .and_then(|value| {
+                layers.overrides.insert(field.clone(), (value, source));
+                layers.apply(&layers.file)
+            });
+        match result {
+            Ok(applied) => config = applied,
+            Err(e) => {
+                match previous {
+                    Some(previous) => layers.overrides.insert(field.clone(), previous),
+                    None => layers.overrides.remove(&field),
+                };
+                let warning = format!("Ignoring invalid value for {}: {}", name, e);
+                tracing::warn!("{}", warning);
+                warnings.push(warning);
+            }
+        }
+    }
+
+    Ok(LayeredConfig { config, layers, warnings })
+}
+
+/// Top-level fields that can be overridden, in name order
+fn overridable_fields() -> Result<Vec<String>, AppError> {
+    // Fields skipped when empty still need to be overridable
+    let mut fields: Vec<String> = to_object(&Config::default())?.keys().cloned().collect();
+    fields.push("auth_token".to_string());
+    fields.retain(|f| f != "auth_token_ref");
+    fields.sort();
+    fields.dedup();
+    Ok(fields)
+}
+
+/// Names of the top-level fields present in config.json
+fn read_file_fields() -> Result<Vec<String>, AppError> {
+    let path = get_config_path();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let contents = fs::read_to_string(&path)
+        .map_err(|e| AppError::Config(format!("Failed to read config: {}", e)))?;
+    let value: Value = serde_json::from_str(&contents)
+        .map_err(|e| AppError::Config(format!("Failed to parse config: {}", e)))?;
+
+    Ok(value.as_object().map(|o| o.keys().cloned().collect()).unwrap_or_default())
+}
+
+/// Serialize a config into a JSON object
+fn to_object(config: &Config) -> Result<Map<String, Value>, AppError> {
+    match serde_json::to_value(config) {
+        Ok(Value::Object(map)) => Ok(map),
+        Ok(_) => Err(AppError::Config("Config did not serialize to an object".to_string())),
+        Err(e) => Err(AppError::Config(format!("Failed to serialize config: {}", e))),
+    }
+}
+
+/// Interpret a raw override string according to the type of the value it replaces
+///
+/// String and optional fields take the raw text; everything else is parsed as JSON.
+fn parse_override(raw: &str, current: Option<&Value>) -> Result<Value, serde_json::Error> {
+    match current {
+        None | Some(Value::Null) | Some(Value::String(_)) => Ok(Value::String(raw.to_string())),
+        Some(_) => serde_json::from_str(raw),
+    }
+}
+
+/// Collect `--field value` and `--field=value` pairs for known fields
+fn parse_args(args: &[String], fields: &[String]) -> Vec<(String, String)> {
+    let mut overrides = Vec::new();
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        let Some(flag) = arg.strip_prefix("--") else {
+            continue;
+        };
+
+        let (name, inline_value) = match flag.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (flag, None),
+        };
+
+        let field = name.replace('-', "_");
+        if !fields.contains(&field) {
+            continue;
+        }
+
+        if let Some(value) = inline_value.or_else(|| iter.next().cloned()) {
+            overrides.push((field, value));
+        }
+    }
+
+    overrides
+}
+
+/// Hide tokens in values reported by `get_config_sources`
+fn mask_tokens(field: &str, value: Value) -> Value {
+    let mask = |token: &Value| match token {
+        Value::String(s) if !s.is_empty() => Value::String("********".to_string()),
+        other => other.clone(),
+    };
+
+    match (field, value) {
+        ("auth_token", token) => mask(&token),
+        ("profiles", Value::Array(profiles)) => Value::Array(
+            profiles
+                .into_iter()
+                .map(|mut profile| {
+                    if let Some(token) = profile.get_mut("auth_token") {
+                        *token = mask(token);
+                    }
+                    profile
+                })
+                .collect(),
+        ),
+        (_, value) => value,
+    }
+}
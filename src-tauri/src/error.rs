@@ -0,0 +1,172 @@
+use serde::ser::SerializeStruct;
+use crate::types::SectionError;
+use serde::{Serialize, Serializer};
+use std::time::Duration;
+
+/// Errors surfaced by the API client, config storage and Tauri commands
+///
+/// Serialized to the frontend as `{ "kind": "...", "message": "...", "attempts": n }`
+/// so the UI can branch on `kind` instead of matching error strings.
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum AppError {
+    /// The token was rejected (HTTP 401/403)
+    #[error("Authentication failed (HTTP {status}): the auth token is invalid or expired")]
+    Unauthorized { status: u16 },
+
+    /// The API asked us to slow down (HTTP 429)
+    #[error("Rate limited by the API")]
+    RateLimited { retry_after_secs: Option<u64> },
+
+    /// Any other non-success HTTP status
+    #[error("HTTP {status}: {body}")]
+    Http {
+        status: u16,
+        body: String,
+        retry_after_secs: Option<u64>,
+    },
+
+    /// The request never got a response (DNS, connect, TLS, timeout)
+    #[error("Network error: {0}")]
+    Network(String),
+
+    /// The API answered 200 but reported a business failure (`success: false` or `code` other than 200)
+    #[error("API error{}: {msg}", code.map(|c| format!(" {}", c)).unwrap_or_default())]
+    Api { code: Option<i64>, msg: String },
+
+    /// The response body did not match the expected shape
+    #[error("Parse error: {0}")]
+    Parse(String),
+
+    /// The configured base URL or monitor base URL is not a usable http(s) URL
+    #[error("Invalid base URL: {0}")]
+    InvalidBaseUrl(String),
+
+    /// No auth token has been configured yet
+    #[error("No auth token configured")]
+    NotConfigured,
+
+    /// Reading or writing config.json failed
+    #[error("Config error: {0}")]
+    Config(String),
+
+    /// Reading or writing other local files (history, state) failed
+    #[error("Storage error: {0}")]
+    Storage(String),
+
+    /// Any other local I/O failure, such as starting the async runtime
+    #[error("I/O error: {0}")]
+    Io(String),
+
+    /// A command was called with invalid arguments
+    #[error("{0}")]
+    InvalidInput(String),
+
+    /// Window, tray or other Tauri runtime failure
+    #[error("{0}")]
+    Tauri(String),
+
+    /// A retryable request still failed after the configured number of attempts
+    #[error("{last} (after {attempts} attempts)")]
+    RetriesExhausted { attempts: u32, last: Box<AppError> },
+}
+
+impl AppError {
+    /// Machine-readable identifier for the error variant
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppError::Unauthorized { .. } => "unauthorized",
+            AppError::RateLimited { .. } => "rate_limited",
+            AppError::Http { .. } => "http",
+            AppError::Network(_) => "network",
+            AppError::Api { .. } => "api",
+            AppError::Parse(_) => "parse",
+            AppError::InvalidBaseUrl(_) => "invalid_base_url",
+            AppError::NotConfigured => "not_configured",
+            AppError::Config(_) => "config",
+            AppError::Storage(_) => "storage",
+            AppError::Io(_) => "io",
+            AppError::InvalidInput(_) => "invalid_input",
+            AppError::Tauri(_) => "tauri",
+            // Report the underlying failure; `attempts` is serialized separately
+            AppError::RetriesExhausted { last, .. } => last.kind(),
+        }
+    }
+
+    /// Whether the user needs to fix their credentials before retrying
+    pub fn is_auth_error(&self) -> bool {
+        match self {
+            AppError::RetriesExhausted { last, .. } => last.is_auth_error(),
+            _ => matches!(self, AppError::Unauthorized { .. } | AppError::NotConfigured),
+        }
+    }
+
+    /// Whether a request failing with this error is worth retrying
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            AppError::Network(_) | AppError::RateLimited { .. } => true,
+            AppError::Http { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
+
+    /// Server-requested wait before retrying (429/503 `Retry-After`)
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            AppError::RateLimited { retry_after_secs } => retry_after_secs.map(Duration::from_secs),
+            AppError::Http { status: 503, retry_after_secs, .. } => {
+                retry_after_secs.map(Duration::from_secs)
+            }
+            _ => None,
+        }
+    }
+
+    /// HTTP status the server answered with, if it answered at all
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            AppError::Unauthorized { status } | AppError::Http { status, .. } => Some(*status),
+            AppError::RateLimited { .. } => Some(429),
+            AppError::RetriesExhausted { last, .. } => last.status(),
+            _ => None,
+        }
+    }
+
+    /// Number of attempts made before giving up, if the request was retried
+    pub fn attempts(&self) -> Option<u32> {
+        match self {
+            AppError::RetriesExhausted { attempts, .. } => Some(*attempts),
+            _ => None,
+        }
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 3)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("attempts", &self.attempts())?;
+        state.end()
+    }
+}
+
+impl From<&AppError> for SectionError {
+    fn from(e: &AppError) -> Self {
+        SectionError {
+            kind: e.kind().to_string(),
+            message: e.to_string(),
+        }
+    }
+}
+
+impl From<reqwest::Error> for AppError {
+    fn from(e: reqwest::Error) -> Self {
+        AppError::Network(e.to_string())
+    }
+}
+
+#[cfg(feature = "gui")]
+impl From<tauri::Error> for AppError {
+    fn from(e: tauri::Error) -> Self {
+        AppError::Tauri(e.to_string())
+    }
+}
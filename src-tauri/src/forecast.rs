@@ -0,0 +1,165 @@
+use crate::types::{AllUsageData, ForecastSource, QuotaForecast, QuotaKind, QuotaLimit, TimeWindow};
+use chrono::NaiveDateTime;
+
+/// How far back snapshots count towards the current burn rate
+pub const LOOKBACK_SECS: i64 = 60 * 60;
+
+/// Minimum span of snapshots before their slope is trusted over the time series
+const MIN_HISTORY_SPAN_SECS: i64 = 10 * 60;
+
+/// Number of complete hourly buckets averaged for the time-series fallback
+const TIMESERIES_BUCKETS: usize = 2;
+
+/// Forecast when the token quota of `data` runs out
+///
+/// The burn rate comes from the slope of quota percentages in `history`
+/// (the profile's recent snapshots, oldest first) when they span at least
+/// ten minutes, otherwise from the last complete hours of the `tokens_usage`
+/// time series. Returns `None` without a token quota or usable data.
+pub fn forecast_token_quota(
+    data: &AllUsageData,
+    history: &[AllUsageData],
+    now: i64,
+) -> Option<QuotaForecast> {
+    let limit = token_limit(data)?;
+    let resets_at = limit.resets_at;
+
+    let (burn_rate_per_hour, source) = history_burn_rate(data, history, limit.window_start)
+        .map(|rate| (rate, ForecastSource::History))
+        .or_else(|| timeseries_burn_rate(data, limit).map(|rate| (rate, ForecastSource::Timeseries)))?;
+
+    let exhausted_at = if limit.percentage >= 100.0 {
+        Some(now)
+    } else if burn_rate_per_hour > 0.0 {
+        let hours_left = (100.0 - limit.percentage) / burn_rate_per_hour;
+        Some(now + (hours_left * 3600.0) as i64)
+    } else {
+        None
+    };
+
+    let exhausted_before_reset = match (exhausted_at, resets_at) {
+        (Some(exhausted), Some(reset)) => exhausted < reset,
+        (Some(_), None) => true,
+        (None, _) => false,
+    };
+
+    Some(QuotaForecast {
+        profile: data.profile.clone(),
+        percentage: limit.percentage,
+        burn_rate_per_hour,
+        source,
+        exhausted_at,
+        resets_at,
+        exhausted_before_reset,
+        computed_at: now,
+    })
+}
+
+/// The token quota of a snapshot
+pub fn token_limit(data: &AllUsageData) -> Option<&QuotaLimit> {
+    data.quota(&QuotaKind::Tokens)
+}
+
+/// Whether a quota has been used up
+pub fn is_exhausted(limit: &QuotaLimit) -> bool {
+    limit.percentage >= 100.0 || limit.remaining == Some(0)
+}
+
+/// When the token quota of a snapshot resets next, in Unix seconds
+pub fn token_reset_at(data: &AllUsageData) -> Option<i64> {
+    token_limit(data).and_then(|l| l.resets_at)
+}
+
+/// Percentage points per hour between the oldest usable snapshot and `data`
+fn history_burn_rate(data: &AllUsageData, history: &[AllUsageData], window_start: Option<i64>) -> Option<f64> {
+    let current = token_limit(data)?.percentage;
+
+    // Snapshots from before the last reset describe a different quota window
+    let since = (data.timestamp - LOOKBACK_SECS).max(window_start.unwrap_or(i64::MIN));
+
+    let mut samples: Vec<(i64, f64)> = history
+        .iter()
+        .filter(|s| s.profile == data.profile && s.status.quota.error.is_none())
+        .filter(|s| s.timestamp >= since && s.timestamp < data.timestamp)
+        .filter_map(|s| token_limit(s).map(|l| (s.timestamp, l.percentage)))
+        .collect();
+    samples.push((data.timestamp, current));
+
+    // A drop in percentage means the quota reset in between; only keep what came after
+    if let Some(reset_index) = samples.windows(2).rposition(|pair| pair[1].1 < pair[0].1) {
+        samples.drain(..=reset_index);
+    }
+
+    let (first_time, first_pct) = *samples.first()?;
+    let span = data.timestamp - first_time;
+    if span < MIN_HISTORY_SPAN_SECS {
+        return None;
+    }
+
+    Some(((current - first_pct) / (span as f64 / 3600.0)).max(0.0))
+}
+
+/// Percentage points per hour from the average of the last complete hourly buckets
+///
+/// Series with wider buckets, such as the daily ones of longer windows, are not used.
+fn timeseries_burn_rate(data: &AllUsageData, limit: &QuotaLimit) -> Option<f64> {
+    let series = data.model_usage_timeseries.as_ref()?;
+    let hourly = match bucket_secs(&series.x_time) {
+        Some(secs) => secs == 3600,
+        // Without readable labels, trust only the windows the API answers by the hour
+        None => matches!(
+            data.time_window,
+            TimeWindow::Last24Hours | TimeWindow::Last5Hours | TimeWindow::Today
+        ),
+    };
+    if !hourly {
+        return None;
+    }
+
+    // The last bucket is the hour in progress
+    let complete = series.tokens_usage.len().checked_sub(1)?;
+    let buckets: Vec<i64> = series.tokens_usage[..complete]
+        .iter()
+        .rev()
+        .take(TIMESERIES_BUCKETS)
+        .map(|tokens| tokens.unwrap_or(0))
+        .collect();
+    if buckets.is_empty() {
+        return None;
+    }
+    let tokens_per_hour = buckets.iter().sum::<i64>() as f64 / buckets.len() as f64;
+
+    // Convert tokens to percentage points using the quota size, or the ratio used so far
+    let percent_per_token = match (limit.usage, limit.current_value) {
+        (Some(total), _) if total > 0 => 100.0 / total as f64,
+        (_, Some(used)) if used > 0 && limit.percentage > 0.0 => limit.percentage / used as f64,
+        _ => return None,
+    };
+
+    Some(tokens_per_hour * percent_per_token)
+}
+
+/// Width of the time series buckets, from the last two `x_time` labels
+fn bucket_secs(x_time: &[String]) -> Option<i64> {
+    let parse = |label: &String| NaiveDateTime::parse_from_str(label, "%Y-%m-%d %H:%M").ok();
+    let [.., previous, last] = x_time else {
+        return None;
+    };
+    Some((parse(last)? - parse(previous)?).num_seconds())
+}
+
+/// Format a countdown in seconds as "HH:MM", rounding up to the minute
+pub fn format_countdown(secs: i64) -> String {
+    let minutes = (secs.max(0) + 59) / 60;
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// Format a duration in seconds as e.g. "42m" or "1h 5m"
+pub fn format_duration(secs: i64) -> String {
+    let minutes = (secs.max(0) + 59) / 60;
+    if minutes < 60 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}h {}m", minutes / 60, minutes % 60)
+    }
+}
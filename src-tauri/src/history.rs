@@ -0,0 +1,235 @@
+use crate::error::AppError;
+use crate::types::AllUsageData;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Bytes read per step when scanning the history backwards
+const TAIL_CHUNK: u64 = 64 * 1024;
+
+/// How far out of order appends can be
+///
+/// Refreshes of different profiles run concurrently, so a snapshot can be
+/// appended after a newer one from another profile.
+const ORDER_SLACK_SECS: i64 = 10 * 60;
+
+/// Append-only usage history stored as JSON lines next to `config.json`
+///
+/// Every refresh appends one `AllUsageData` snapshot per line, so quota
+/// percentages, model time series and tool counts are all kept.
+pub struct HistoryStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl HistoryStore {
+    /// Create a store backed by the given file
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Create a store in the default app data directory
+    pub fn open_default() -> Self {
+        Self::new(crate::config::get_data_dir().join("history.jsonl"))
+    }
+
+    /// Append a snapshot to the history file
+    pub fn append(&self, data: &AllUsageData) -> Result<(), AppError> {
+        let _guard = self.lock.lock().unwrap();
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| AppError::Storage(format!("Failed to create history directory: {}", e)))?;
+        }
+
+        let line = serde_json::to_string(data)
+            .map_err(|e| AppError::Storage(format!("Failed to serialize snapshot: {}", e)))?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| AppError::Storage(format!("Failed to open history: {}", e)))?;
+
+        writeln!(file, "{}", line)
+            .map_err(|e| AppError::Storage(format!("Failed to write history: {}", e)))
+    }
+
+    /// Return snapshots with `start <= timestamp <= end`, oldest first
+    ///
+    /// Either bound may be omitted to leave that side of the range open;
+    /// `profile` restricts the result to a single profile. With a `start`
+    /// only the tail of the file back to that time is read.
+    pub fn query(
+        &self,
+        start: Option<i64>,
+        end: Option<i64>,
+        profile: Option<&str>,
+    ) -> Result<Vec<AllUsageData>, AppError> {
+        let _guard = self.lock.lock().unwrap();
+
+        let snapshots = match start {
+            Some(start) => self.read_since(start)?,
+            None => self.read_all()?,
+        };
+        let snapshots = snapshots
+            .into_iter()
+            .filter(|s| start.is_none_or(|start| s.timestamp >= start))
+            .filter(|s| end.is_none_or(|end| s.timestamp <= end))
+            .filter(|s| profile.is_none_or(|profile| s.profile == profile))
+            .collect();
+
+        Ok(snapshots)
+    }
+
+    /// Drop snapshots older than `retention_days`; 0 keeps everything
+    ///
+    /// Only rewrites the file when the oldest entry has actually expired,
+    /// so it is cheap to call after every append.
+    pub fn prune(&self, retention_days: u64) -> Result<usize, AppError> {
+        if retention_days == 0 {
+            return Ok(0);
+        }
+
+        let _guard = self.lock.lock().unwrap();
+        let cutoff = chrono::Local::now().timestamp() - (retention_days as i64) * 24 * 60 * 60;
+
+        match self.read_oldest_timestamp()? {
+            Some(oldest) if oldest < cutoff => {}
+            _ => return Ok(0),
+        }
+
+        let snapshots = self.read_all()?;
+        let before = snapshots.len();
+        let kept: Vec<&AllUsageData> = snapshots.iter().filter(|s| s.timestamp >= cutoff).collect();
+
+        let mut contents = String::new();
+        for snapshot in &kept {
+            let line = serde_json::to_string(snapshot)
+                .map_err(|e| AppError::Storage(format!("Failed to serialize snapshot: {}", e)))?;
+            contents.push_str(&line);
+            contents.push('\n');
+        }
+
+        // Write to a temp file first so a crash never leaves a truncated history
+        let tmp_path = self.path.with_extension("jsonl.tmp");
+        fs::write(&tmp_path, contents)
+            .map_err(|e| AppError::Storage(format!("Failed to write history: {}", e)))?;
+        fs::rename(&tmp_path, &self.path)
+            .map_err(|e| AppError::Storage(format!("Failed to replace history: {}", e)))?;
+
+        Ok(before - kept.len())
+    }
+
+    /// Delete all stored history
+    pub fn clear(&self) -> Result<(), AppError> {
+        let _guard = self.lock.lock().unwrap();
+
+        if self.path.exists() {
+            fs::remove_file(&self.path)
+                .map_err(|e| AppError::Storage(format!("Failed to clear history: {}", e)))?;
+        }
+        Ok(())
+    }
+
+    /// Read every snapshot, skipping lines that fail to parse
+    fn read_all(&self) -> Result<Vec<AllUsageData>, AppError> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let file = File::open(&self.path)
+            .map_err(|e| AppError::Storage(format!("Failed to open history: {}", e)))?;
+
+        let mut snapshots = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|e| AppError::Storage(format!("Failed to read history: {}", e)))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            // A partially written last line (e.g. after a crash) should not poison the whole history
+            if let Ok(snapshot) = serde_json::from_str::<AllUsageData>(&line) {
+                snapshots.push(snapshot);
+            }
+        }
+
+        Ok(snapshots)
+    }
+
+    /// Read snapshots from the end of the file back to the first one older than `start`
+    ///
+    /// Like `read_all`, lines that fail to parse are skipped. Snapshots
+    /// slightly older than `start` may be included.
+    fn read_since(&self, start: i64) -> Result<Vec<AllUsageData>, AppError> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let read_error = |e: std::io::Error| AppError::Storage(format!("Failed to read history: {}", e));
+        let mut file = File::open(&self.path)
+            .map_err(|e| AppError::Storage(format!("Failed to open history: {}", e)))?;
+        let mut pos = file.metadata().map_err(read_error)?.len();
+
+        // Newest first; `partial` is the start of a line whose beginning is in an earlier chunk
+        let mut snapshots = Vec::new();
+        let mut partial = Vec::new();
+        while pos > 0 {
+            let len = TAIL_CHUNK.min(pos);
+            pos -= len;
+            let mut chunk = vec![0; len as usize];
+            file.seek(SeekFrom::Start(pos)).map_err(read_error)?;
+            file.read_exact(&mut chunk).map_err(read_error)?;
+            chunk.extend_from_slice(&partial);
+
+            let complete_from = if pos == 0 {
+                0
+            } else {
+                match chunk.iter().position(|&b| b == b'\n') {
+                    Some(newline) => newline + 1,
+                    None => {
+                        partial = chunk;
+                        continue;
+                    }
+                }
+            };
+            partial = chunk[..complete_from].to_vec();
+
+            for line in chunk[complete_from..].split(|&b| b == b'\n').rev() {
+                let Ok(snapshot) = serde_json::from_slice::<AllUsageData>(line) else {
+                    continue;
+                };
+                if snapshot.timestamp < start - ORDER_SLACK_SECS {
+                    snapshots.reverse();
+                    return Ok(snapshots);
+                }
+                snapshots.push(snapshot);
+            }
+        }
+
+        snapshots.reverse();
+        Ok(snapshots)
+    }
+
+    /// Read the timestamp of the first snapshot without loading the whole file
+    fn read_oldest_timestamp(&self) -> Result<Option<i64>, AppError> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+
+        let file = File::open(&self.path)
+            .map_err(|e| AppError::Storage(format!("Failed to open history: {}", e)))?;
+
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|e| AppError::Storage(format!("Failed to read history: {}", e)))?;
+            if let Ok(snapshot) = serde_json::from_str::<AllUsageData>(&line) {
+                return Ok(Some(snapshot.timestamp));
+            }
+        }
+
+        Ok(None)
+    }
+}
@@ -0,0 +1,49 @@
+use crate::types::{QuotaKind, QuotaLimit, QuotaWindow, WindowUnit};
+
+/// Display strings for one language
+///
+/// Data structures only carry stable identifiers; everything shown to the
+/// user is built from one of these tables.
+pub struct Labels {
+    pub tokens: &'static str,
+    pub time: &'static str,
+    pub minute: &'static str,
+    pub hour: &'static str,
+    pub day: &'static str,
+    pub month: &'static str,
+}
+
+pub const ENGLISH: Labels = Labels {
+    tokens: "Token usage",
+    time: "MCP usage",
+    minute: "Minute",
+    hour: "Hour",
+    day: "Day",
+    month: "Month",
+};
+
+/// Label for a quota in English, e.g. "Token usage (5 Hour)"
+pub fn quota_label(limit: &QuotaLimit) -> String {
+    quota_label_in(limit, &ENGLISH)
+}
+
+/// Label for a quota, with the window taken from `unit` and `number`
+///
+/// Unknown kinds show their raw identifier; unknown units leave out the window.
+pub fn quota_label_in(limit: &QuotaLimit, labels: &Labels) -> String {
+    let name = match &limit.kind {
+        QuotaKind::Tokens => labels.tokens,
+        QuotaKind::Time => labels.time,
+        QuotaKind::Unknown(raw) => raw,
+    };
+
+    let window = QuotaWindow::from_raw(limit.unit, limit.number);
+    let unit = match window.unit {
+        WindowUnit::Minute => labels.minute,
+        WindowUnit::Hour => labels.hour,
+        WindowUnit::Day => labels.day,
+        WindowUnit::Month => labels.month,
+        WindowUnit::Unknown => return name.to_string(),
+    };
+    format!("{} ({} {})", name, window.number, unit)
+}
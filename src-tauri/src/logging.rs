@@ -0,0 +1,353 @@
+//! Redacting, size-rotated log files for `tracing`
+//!
+//! The subscriber is a small hand-written one rather than `tracing-subscriber`
+//! and `tracing-appender`: redaction has to see each formatted line before it is
+//! written, and the app only needs one rotating file plus stderr.
+
+use crate::error::AppError;
+use crate::types::Config;
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock, RwLock};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Metadata, Subscriber};
+
+/// Name of the current log file; rotated files get a `.1`, `.2`, ... suffix
+pub const LOG_FILE_NAME: &str = "zai-usage-monitor.log";
+
+/// Size at which the log file is rotated
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+
+/// Log files kept, including the current one
+const MAX_LOG_FILES: usize = 5;
+
+/// Log lines included in a diagnostic bundle
+const DIAGNOSTIC_LOG_LINES: usize = 500;
+
+/// Replacement for redacted values
+const REDACTED: &str = "[REDACTED]";
+
+/// Most verbose level written, changed by `configure`
+static MAX_LEVEL: RwLock<Level> = RwLock::new(Level::INFO);
+
+/// Configured secrets (tokens, webhook secrets and URLs) removed from every log line
+static SECRETS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Directory holding the log files
+pub fn log_dir() -> PathBuf {
+    crate::config::get_data_dir().join("logs")
+}
+
+/// Install the global `tracing` subscriber writing to the log directory and stderr
+///
+/// Logs at info level until `configure` applies the config.
+pub fn init() {
+    let subscriber = LogSubscriber {
+        file: Mutex::new(RotatingFile::new(log_dir().join(LOG_FILE_NAME), MAX_LOG_BYTES, MAX_LOG_FILES)),
+        next_span: AtomicU64::new(1),
+    };
+    if tracing::subscriber::set_global_default(subscriber).is_err() {
+        tracing::warn!("A log subscriber was already installed");
+    }
+}
+
+/// Apply the configured level and register the config's secrets for redaction
+///
+/// Called once at startup and again after each config edit from the webview.
+pub fn configure(config: &Config) {
+    let level = parse_level(&config.log_level).unwrap_or_else(|e| {
+        tracing::warn!("{}", e);
+        Level::INFO
+    });
+    *MAX_LEVEL.write().unwrap() = level;
+    *SECRETS.write().unwrap() = config_secrets(config);
+}
+
+/// Parse a level name: "error", "warn", "info", "debug" or "trace"
+pub fn parse_level(name: &str) -> Result<Level, AppError> {
+    name.trim()
+        .parse()
+        .map_err(|_| AppError::InvalidInput(format!("Invalid log level: {} (use error, warn, info, debug or trace)", name)))
+}
+
+/// Every secret value in a config
+fn config_secrets(config: &Config) -> Vec<String> {
+    let mut secrets: Vec<String> = config
+        .all_profiles()
+        .into_iter()
+        .map(|profile| profile.auth_token)
+        .chain(config.webhooks.iter().flat_map(|hook| hook.secret.clone()))
+        // Webhook URLs such as Slack's carry their credentials in the path
+        .chain(config.webhooks.iter().map(|hook| hook.url.clone()))
+        .filter(|secret| secret.len() >= 4)
+        .collect();
+
+    // Replace longer secrets first so one containing another is removed whole
+    secrets.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+    secrets.dedup();
+    secrets
+}
+
+/// Patterns for credentials and personal data that aren't known secrets
+fn patterns() -> &'static [(Regex, &'static str)] {
+    static PATTERNS: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        [
+            (r"(?i)\b(bearer\s+)[A-Za-z0-9._~+/=-]+", "${1}[REDACTED]"),
+            (
+                r#"(?i)("?\b(?:auth_token|access_token|token|secret|password|authorization|api_?key|email|phone|mobile|user_?id)"?\s*[:=]\s*)"[^"]*""#,
+                "${1}\"[REDACTED]\"",
+            ),
+            (
+                r#"(?i)(\b(?:auth_token|access_token|token|secret|password|authorization|api_?key|email|phone|mobile|user_?id)\s*[:=]\s*)(?:bearer\s+)?[^\s"',;&}]+"#,
+                "${1}[REDACTED]",
+            ),
+            (r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b", "[EMAIL]"),
+            (r"\bsk-[A-Za-z0-9_-]{8,}", REDACTED),
+        ]
+        .into_iter()
+        .map(|(pattern, replacement)| (Regex::new(pattern).unwrap(), replacement))
+        .collect()
+    })
+}
+
+/// Remove configured secrets, credentials and personal data from a string
+pub fn redact(text: &str) -> String {
+    let mut text = text.to_string();
+    for secret in SECRETS.read().unwrap().iter() {
+        text = text.replace(secret.as_str(), REDACTED);
+    }
+    for (pattern, replacement) in patterns() {
+        text = pattern.replace_all(&text, *replacement).into_owned();
+    }
+    text
+}
+
+/// Redact a JSON value: sensitive keys lose their value, every other string goes through `redact`
+pub fn redact_value(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if is_sensitive_key(key) && !value.is_null() {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact_value(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_value),
+        Value::String(text) => *text = redact(text),
+        _ => {}
+    }
+}
+
+/// Whether a JSON key names a credential or personal field, e.g. `auth_token` or `userEmail`
+fn is_sensitive_key(key: &str) -> bool {
+    let key: String = key.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_ascii_lowercase();
+    ["token", "secret", "password", "authorization", "apikey", "email", "phone", "mobile"]
+        .iter()
+        .any(|sensitive| key.ends_with(sensitive))
+}
+
+/// Log file that starts a new file once it reaches `max_bytes`
+///
+/// The current file is renamed to `<name>.1`, older ones shift up, and only
+/// `max_files` files are kept.
+pub struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: Option<File>,
+    size: u64,
+}
+
+impl RotatingFile {
+    pub fn new(path: PathBuf, max_bytes: u64, max_files: usize) -> Self {
+        Self {
+            path,
+            max_bytes,
+            max_files: max_files.max(1),
+            file: None,
+            size: 0,
+        }
+    }
+
+    /// Append a line, rotating first if it would not fit
+    pub fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        if self.file.is_none() {
+            self.open()?;
+        }
+        if self.size > 0 && self.size + line.len() as u64 + 1 > self.max_bytes {
+            self.rotate()?;
+        }
+
+        let file = self.file.as_mut().expect("log file is open");
+        writeln!(file, "{}", line)?;
+        self.size += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    fn open(&mut self) -> std::io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = file.metadata()?.len();
+        self.file = Some(file);
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file = None;
+        let _ = fs::remove_file(rotated_path(&self.path, self.max_files - 1));
+        for index in (1..self.max_files - 1).rev() {
+            let _ = fs::rename(rotated_path(&self.path, index), rotated_path(&self.path, index + 1));
+        }
+        if self.max_files > 1 {
+            fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        } else {
+            fs::remove_file(&self.path)?;
+        }
+        self.open()
+    }
+}
+
+/// Path of rotated file number `index`; 0 is the current file
+pub fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    if index == 0 {
+        return path.to_path_buf();
+    }
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}", index));
+    path.with_file_name(name)
+}
+
+/// The last `limit` lines across the current and rotated log files, oldest first
+pub fn recent_lines(path: &Path, limit: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for index in (0..MAX_LOG_FILES).rev() {
+        let Ok(file) = File::open(rotated_path(path, index)) else {
+            continue;
+        };
+        lines.extend(BufReader::new(file).lines().map_while(Result::ok));
+    }
+    let skip = lines.len().saturating_sub(limit);
+    lines.split_off(skip)
+}
+
+/// Write a diagnostic bundle to the data directory and return its path
+///
+/// Adds the generation time and the most recent log lines to `bundle`, then
+/// runs the whole bundle through `redact_value` before it touches the disk.
+pub fn write_diagnostics(mut bundle: Value) -> Result<PathBuf, AppError> {
+    let now = chrono::Local::now();
+    if let Value::Object(map) = &mut bundle {
+        map.insert("generated_at".to_string(), Value::String(now.to_rfc3339()));
+        map.insert(
+            "logs".to_string(),
+            Value::from(recent_lines(&log_dir().join(LOG_FILE_NAME), DIAGNOSTIC_LOG_LINES)),
+        );
+    }
+    redact_value(&mut bundle);
+
+    let dir = crate::config::get_data_dir().join("diagnostics");
+    fs::create_dir_all(&dir)
+        .map_err(|e| AppError::Storage(format!("Failed to create diagnostics directory: {}", e)))?;
+    let path = dir.join(format!("diagnostics-{}.json", now.format("%Y%m%d-%H%M%S")));
+
+    let contents = serde_json::to_string_pretty(&bundle)
+        .map_err(|e| AppError::Storage(format!("Failed to serialize diagnostics: {}", e)))?;
+    fs::write(&path, contents)
+        .map_err(|e| AppError::Storage(format!("Failed to write diagnostics: {}", e)))?;
+
+    tracing::info!(path = %path.display(), "Exported diagnostics");
+    Ok(path)
+}
+
+/// Writes each event as a redacted JSON line to the log file and a readable line to stderr
+struct LogSubscriber {
+    file: Mutex<RotatingFile>,
+    next_span: AtomicU64,
+}
+
+impl Subscriber for LogSubscriber {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        *metadata.level() <= *MAX_LEVEL.read().unwrap()
+    }
+
+    fn new_span(&self, _span: &Attributes<'_>) -> Id {
+        Id::from_u64(self.next_span.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let metadata = event.metadata();
+        let mut fields = FieldVisitor(Map::new());
+        event.record(&mut fields);
+        let mut fields = fields.0;
+        let message = fields.remove("message").unwrap_or(Value::String(String::new()));
+
+        let timestamp = chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, false);
+        let extra: Vec<String> = fields.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+        eprintln!(
+            "{} {:>5} {}: {} {}",
+            timestamp,
+            metadata.level(),
+            metadata.target(),
+            message.as_str().unwrap_or_default(),
+            extra.join(" ")
+        );
+
+        let mut line = Map::new();
+        line.insert("timestamp".to_string(), Value::String(timestamp));
+        line.insert("level".to_string(), Value::String(metadata.level().to_string()));
+        line.insert("target".to_string(), Value::String(metadata.target().to_string()));
+        line.insert("message".to_string(), message);
+        line.extend(fields);
+
+        // Logging must never take the app down
+        let _ = self.file.lock().unwrap().write_line(&Value::Object(line).to_string());
+    }
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+/// Collects event fields as JSON, redacting every string
+struct FieldVisitor(Map<String, Value>);
+
+impl Visit for FieldVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name().to_string(), Value::String(redact(&format!("{:?}", value))));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), Value::String(redact(value)));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+}
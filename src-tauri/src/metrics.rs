@@ -0,0 +1,271 @@
+//! Prometheus text exposition of the latest usage
+//!
+//! `render` builds without GUI libraries; the listener serving it needs the
+//! `gui` feature, as it reads from the tray app's `AppState`.
+
+use crate::api::UsageClient;
+#[cfg(feature = "gui")]
+use crate::commands::AppState;
+use crate::types::{AllUsageData, Config};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+#[cfg(feature = "gui")]
+use std::sync::Mutex;
+#[cfg(feature = "gui")]
+use tauri::async_runtime::JoinHandle;
+#[cfg(feature = "gui")]
+use tauri::{AppHandle, Manager, Runtime};
+#[cfg(feature = "gui")]
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+#[cfg(feature = "gui")]
+use tokio::net::TcpListener;
+
+/// Optional local HTTP listener serving the latest usage in Prometheus text format
+///
+/// Only `GET /metrics` is answered; everything else gets a 404. The listener
+/// reads from `AppState` on every scrape, so it never triggers API requests.
+#[cfg(feature = "gui")]
+pub struct MetricsServer {
+    handle: Mutex<Option<JoinHandle<()>>>,
+    address: Mutex<Option<String>>,
+}
+
+#[cfg(feature = "gui")]
+impl MetricsServer {
+    pub fn new() -> Self {
+        Self {
+            handle: Mutex::new(None),
+            address: Mutex::new(None),
+        }
+    }
+
+    /// Start (or restart) listening on `address`; `None` stops the listener
+    pub fn start<R: Runtime>(&self, app: AppHandle<R>, address: Option<String>) {
+        self.stop();
+
+        let Some(address) = address.filter(|a| !a.trim().is_empty()) else {
+            return;
+        };
+
+        let bind_address = address.clone();
+        let handle = tauri::async_runtime::spawn(async move {
+            let listener = match TcpListener::bind(&bind_address).await {
+                Ok(listener) => listener,
+                Err(e) => {
+                    tracing::error!(address = %bind_address, error = %e, "Failed to start metrics listener");
+                    return;
+                }
+            };
+            tracing::info!("Serving metrics on http://{}/metrics", bind_address);
+
+            loop {
+                let Ok((stream, _)) = listener.accept().await else {
+                    continue;
+                };
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = serve(stream, &app).await {
+                        tracing::debug!(error = %e, "Metrics request failed");
+                    }
+                });
+            }
+        });
+
+        *self.handle.lock().unwrap() = Some(handle);
+        *self.address.lock().unwrap() = Some(address);
+    }
+
+    /// Restart the listener only if the address differs from the running one
+    pub fn restart_if_changed<R: Runtime>(&self, app: AppHandle<R>, address: Option<String>) {
+        let address = address.filter(|a| !a.trim().is_empty());
+        if *self.address.lock().unwrap() != address {
+            self.start(app, address);
+        }
+    }
+
+    /// Stop the listener if it is running
+    pub fn stop(&self) {
+        if let Some(handle) = self.handle.lock().unwrap().take() {
+            handle.abort();
+        }
+        *self.address.lock().unwrap() = None;
+    }
+}
+
+#[cfg(feature = "gui")]
+impl Default for MetricsServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Answer a single HTTP request and close the connection
+#[cfg(feature = "gui")]
+async fn serve<R: Runtime>(mut stream: tokio::net::TcpStream, app: &AppHandle<R>) -> std::io::Result<()> {
+    // The request line is all we need; scrapers send small requests
+    let mut buffer = [0u8; 1024];
+    let read = stream.read(&mut buffer).await?;
+    let request = String::from_utf8_lossy(&buffer[..read]);
+    let mut parts = request.lines().next().unwrap_or_default().split_whitespace();
+    let (method, path) = (parts.next(), parts.next());
+
+    let (status, content_type, body) = match (method, path) {
+        (Some("GET"), Some("/metrics")) => {
+            let state = app.state::<AppState>();
+            let config = state.config.lock().unwrap().clone();
+            let usage_by_profile = state.usage_by_profile.lock().unwrap().clone();
+            ("200 OK", "text/plain; version=0.0.4", render(&config, &usage_by_profile))
+        }
+        _ => ("404 Not Found", "text/plain", "Not found\n".to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// One metric family: name, type, help text and its samples
+struct Family {
+    name: &'static str,
+    kind: &'static str,
+    help: &'static str,
+    samples: Vec<(Vec<(&'static str, String)>, f64)>,
+}
+
+impl Family {
+    fn new(name: &'static str, kind: &'static str, help: &'static str) -> Self {
+        Self {
+            name,
+            kind,
+            help,
+            samples: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, labels: Vec<(&'static str, String)>, value: f64) {
+        self.samples.push((labels, value));
+    }
+}
+
+/// Render the latest usage of every profile in Prometheus text exposition format
+///
+/// Model and tool figures are totals over each profile's current time window,
+/// which is exposed as the `window` label. They are gauges, since changing the
+/// window or sliding it forward can make them go down.
+pub fn render(config: &Config, usage_by_profile: &BTreeMap<String, AllUsageData>) -> String {
+    let mut quota_percentage = Family::new("zai_quota_percentage", "gauge", "Quota used, in percent");
+    let mut quota_current = Family::new("zai_quota_current_value", "gauge", "Quota consumed so far");
+    let mut quota_remaining = Family::new("zai_quota_remaining", "gauge", "Quota left before the limit");
+    let mut quota_reset = Family::new(
+        "zai_quota_next_reset_timestamp_seconds",
+        "gauge",
+        "Unix time at which the quota resets",
+    );
+    let mut quota_window = Family::new(
+        "zai_quota_window_seconds",
+        "gauge",
+        "Length of the quota window, counting a month as 30 days",
+    );
+    let mut model_calls = Family::new("zai_model_calls", "gauge", "Model calls in the time window");
+    let mut model_tokens = Family::new("zai_model_tokens", "gauge", "Tokens used in the time window");
+    let mut tool_calls = Family::new("zai_tool_calls", "gauge", "Tool calls in the time window");
+    let mut last_refresh = Family::new(
+        "zai_last_refresh_timestamp_seconds",
+        "gauge",
+        "Unix time of the last refresh",
+    );
+
+    for (profile, data) in usage_by_profile {
+        let profile_config = config
+            .find_profile(profile)
+            .map(|p| config.for_profile(&p))
+            .unwrap_or_else(|| config.clone());
+        let base_domain =
+            UsageClient::monitor_root(&profile_config).unwrap_or(profile_config.base_url);
+        let labels = || vec![("profile", profile.clone()), ("base_domain", base_domain.clone())];
+
+        let window = serde_json::to_value(&data.time_window)
+            .ok()
+            .and_then(|v| v.get("kind").and_then(|k| k.as_str()).map(str::to_string))
+            .unwrap_or_default();
+        let window_labels = || {
+            let mut labels = labels();
+            labels.push(("window", window.clone()));
+            labels
+        };
+
+        for limit in &data.quota_limits {
+            let quota_labels = || {
+                let mut labels = labels();
+                labels.push(("type", limit.kind.as_raw().to_string()));
+                labels
+            };
+            quota_percentage.push(quota_labels(), limit.percentage);
+            if let Some(current) = limit.current_value {
+                quota_current.push(quota_labels(), current as f64);
+            }
+            if let Some(remaining) = limit.remaining {
+                quota_remaining.push(quota_labels(), remaining as f64);
+            }
+            if let Some(reset) = limit.resets_at {
+                quota_reset.push(quota_labels(), reset as f64);
+            }
+            if let Some(secs) = limit.window.and_then(|w| w.approx_secs()) {
+                quota_window.push(quota_labels(), secs as f64);
+            }
+        }
+
+        for item in &data.model_usage {
+            let mut labels = window_labels();
+            labels.push(("model", item.model.clone()));
+            model_calls.push(labels.clone(), item.request_count as f64);
+            model_tokens.push(labels, item.token_count as f64);
+        }
+
+        for item in &data.tool_usage {
+            let mut labels = window_labels();
+            labels.push(("tool", item.tool_name.clone()));
+            tool_calls.push(labels, item.usage_count as f64);
+        }
+
+        last_refresh.push(labels(), data.timestamp as f64);
+    }
+
+    let mut output = String::new();
+    for family in [
+        quota_percentage,
+        quota_current,
+        quota_remaining,
+        quota_reset,
+        quota_window,
+        model_calls,
+        model_tokens,
+        tool_calls,
+        last_refresh,
+    ] {
+        let _ = writeln!(output, "# HELP {} {}", family.name, family.help);
+        let _ = writeln!(output, "# TYPE {} {}", family.name, family.kind);
+        for (labels, value) in family.samples {
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|(name, value)| format!("{}=\"{}\"", name, escape_label(value)))
+                .collect();
+            let _ = writeln!(output, "{}{{{}}} {}", family.name, labels.join(","), value);
+        }
+    }
+    output
+}
+
+/// Escape a label value as required by the exposition format
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,240 @@
+use crate::error::AppError;
+use crate::types::{AlertMetric, AlertSeverity, UsageAlert};
+use std::sync::{Arc, Mutex};
+
+/// How long "Snooze 1h" silences desktop notifications
+pub const SNOOZE_SECS: i64 = 60 * 60;
+
+/// Buttons offered on an alert notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationAction {
+    OpenDashboard,
+    Snooze,
+}
+
+impl NotificationAction {
+    pub fn id(self) -> &'static str {
+        match self {
+            NotificationAction::OpenDashboard => "open_dashboard",
+            NotificationAction::Snooze => "snooze",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            NotificationAction::OpenDashboard => "Open dashboard",
+            NotificationAction::Snooze => "Snooze 1h",
+        }
+    }
+
+    /// Map a backend response to an action
+    ///
+    /// Platforms report either the identifier or the label; a click on the
+    /// notification body ("default") opens the dashboard.
+    pub fn from_response(response: &str) -> Option<Self> {
+        [NotificationAction::OpenDashboard, NotificationAction::Snooze]
+            .into_iter()
+            .find(|action| response == action.id() || response == action.label())
+            .or((response == "default").then_some(NotificationAction::OpenDashboard))
+    }
+}
+
+/// A notification as handed to a `Notifier`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DesktopNotification {
+    pub title: String,
+    pub body: String,
+    pub actions: Vec<NotificationAction>,
+}
+
+/// Called with the action the user picked
+pub type ActionCallback = Box<dyn FnOnce(NotificationAction) + Send>;
+
+/// Backend that puts notifications on screen
+pub trait Notifier: Send + Sync {
+    /// Show a notification; `on_action` runs if the user picks one of its actions
+    fn notify(&self, notification: DesktopNotification, on_action: ActionCallback) -> Result<(), AppError>;
+}
+
+impl<T: Notifier + ?Sized> Notifier for Arc<T> {
+    fn notify(&self, notification: DesktopNotification, on_action: ActionCallback) -> Result<(), AppError> {
+        (**self).notify(notification, on_action)
+    }
+}
+
+/// Native notifications through the OS notification center
+pub struct SystemNotifier;
+
+impl Notifier for SystemNotifier {
+    fn notify(&self, notification: DesktopNotification, on_action: ActionCallback) -> Result<(), AppError> {
+        let mut native = notify_rust::Notification::new();
+        native
+            .appname("Z.ai Usage Monitor")
+            .summary(&notification.title)
+            .body(&notification.body);
+        for action in &notification.actions {
+            native.action(action.id(), action.label());
+        }
+
+        let handle = native
+            .show()
+            .map_err(|e| AppError::Tauri(format!("Failed to show notification: {}", e)))?;
+
+        // Waiting for a response blocks until the notification is dismissed
+        std::thread::spawn(move || {
+            handle.wait_for_action(|response| {
+                if let Some(action) = NotificationAction::from_response(response) {
+                    on_action(action);
+                }
+            });
+        });
+        Ok(())
+    }
+}
+
+/// Notifier that keeps every notification in memory instead of showing it
+///
+/// Lets tests inspect what would have been shown and simulate a click on an action.
+#[derive(Default)]
+pub struct RecordingNotifier {
+    sent: Mutex<Vec<(DesktopNotification, Option<ActionCallback>)>>,
+}
+
+impl RecordingNotifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Notifications shown so far, oldest first
+    pub fn notifications(&self) -> Vec<DesktopNotification> {
+        self.sent.lock().unwrap().iter().map(|(n, _)| n.clone()).collect()
+    }
+
+    /// Simulate the user picking `action` on notification number `index`
+    ///
+    /// Returns false if there is no such notification or it was already answered.
+    pub fn respond(&self, index: usize, action: NotificationAction) -> bool {
+        let callback = self
+            .sent
+            .lock()
+            .unwrap()
+            .get_mut(index)
+            .and_then(|(_, callback)| callback.take());
+
+        match callback {
+            Some(callback) => {
+                callback(action);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Notifier for RecordingNotifier {
+    fn notify(&self, notification: DesktopNotification, on_action: ActionCallback) -> Result<(), AppError> {
+        self.sent.lock().unwrap().push((notification, Some(on_action)));
+        Ok(())
+    }
+}
+
+/// Turns alerts into desktop notifications and handles snoozing
+pub struct NotificationCenter {
+    notifier: Box<dyn Notifier>,
+    snoozed_until: Arc<Mutex<Option<i64>>>,
+}
+
+impl NotificationCenter {
+    pub fn new(notifier: Box<dyn Notifier>) -> Self {
+        Self {
+            notifier,
+            snoozed_until: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Show a notification for `alert` unless notifications are snoozed
+    ///
+    /// "Snooze 1h" is handled here; "Open dashboard" calls `open_dashboard`.
+    /// Returns whether a notification was shown.
+    pub fn notify_alert(
+        &self,
+        alert: &UsageAlert,
+        now: i64,
+        open_dashboard: impl FnOnce() + Send + 'static,
+    ) -> Result<bool, AppError> {
+        self.show(alert_notification(alert), now, open_dashboard)
+    }
+
+    /// Tell the user that `profile`'s token quota can be used again
+    ///
+    /// Like alerts, this is silenced while notifications are snoozed.
+    pub fn notify_quota_available(
+        &self,
+        profile: &str,
+        now: i64,
+        open_dashboard: impl FnOnce() + Send + 'static,
+    ) -> Result<bool, AppError> {
+        self.show(quota_available_notification(profile), now, open_dashboard)
+    }
+
+    fn show(
+        &self,
+        notification: DesktopNotification,
+        now: i64,
+        open_dashboard: impl FnOnce() + Send + 'static,
+    ) -> Result<bool, AppError> {
+        if self.is_snoozed(now) {
+            return Ok(false);
+        }
+
+        let snoozed_until = self.snoozed_until.clone();
+        let on_action: ActionCallback = Box::new(move |action| match action {
+            NotificationAction::OpenDashboard => open_dashboard(),
+            NotificationAction::Snooze => {
+                let until = chrono::Local::now().timestamp() + SNOOZE_SECS;
+                *snoozed_until.lock().unwrap() = Some(until);
+            }
+        });
+
+        self.notifier.notify(notification, on_action)?;
+        Ok(true)
+    }
+
+    /// End of the current snooze, if one is active at `now`
+    pub fn snoozed_until(&self, now: i64) -> Option<i64> {
+        self.snoozed_until.lock().unwrap().filter(|until| *until > now)
+    }
+
+    pub fn is_snoozed(&self, now: i64) -> bool {
+        self.snoozed_until(now).is_some()
+    }
+}
+
+/// Title, body and actions for an alert
+pub fn alert_notification(alert: &UsageAlert) -> DesktopNotification {
+    let title = match alert.severity {
+        AlertSeverity::Critical => "🚨 Critical Usage Alert",
+        AlertSeverity::Warning => "⚠️ Usage Warning",
+        AlertSeverity::Info => "ℹ️ Usage Notice",
+    };
+    let detail = match alert.metric {
+        AlertMetric::Percentage => format!("{:.1}% used", alert.percentage),
+        AlertMetric::Remaining => format!("{} remaining", alert.value),
+        AlertMetric::BurnRate => format!("burning {:.1}%/h", alert.value),
+    };
+
+    DesktopNotification {
+        title: title.to_string(),
+        body: format!("{} ({}): {}", alert.label, alert.profile, detail),
+        actions: vec![NotificationAction::OpenDashboard, NotificationAction::Snooze],
+    }
+}
+
+/// Notification for a token quota that reset after running out
+pub fn quota_available_notification(profile: &str) -> DesktopNotification {
+    DesktopNotification {
+        title: "✅ Token Quota Available".to_string(),
+        body: format!("The token quota for {} has reset and can be used again", profile),
+        actions: vec![NotificationAction::OpenDashboard, NotificationAction::Snooze],
+    }
+}
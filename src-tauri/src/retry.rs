@@ -0,0 +1,73 @@
+use crate::error::AppError;
+use crate::types::RetryPolicy;
+use rand::Rng;
+use std::future::Future;
+use std::time::Duration;
+
+impl RetryPolicy {
+    /// Backoff before retry number `retry` (1-based), before jitter
+    fn base_delay(&self, retry: u32) -> Duration {
+        let factor = self.multiplier.max(1.0).powi(retry.saturating_sub(1) as i32);
+        let millis = (self.initial_backoff_ms as f64 * factor).min(self.max_backoff_ms as f64);
+        Duration::from_millis(millis as u64)
+    }
+
+    /// Backoff before retry number `retry`, spread by +/- `jitter`
+    pub fn delay_for(&self, retry: u32) -> Duration {
+        let base = self.base_delay(retry).as_millis() as f64;
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let spread = if jitter > 0.0 {
+            rand::thread_rng().gen_range(-jitter..=jitter)
+        } else {
+            0.0
+        };
+
+        Duration::from_millis((base * (1.0 + spread)).max(0.0) as u64)
+    }
+}
+
+/// Run `operation` until it succeeds, fails with a non-retryable error,
+/// or `policy.max_attempts` is reached
+///
+/// A server-provided `Retry-After` replaces the computed backoff. If it is
+/// longer than `max_backoff_ms` we give up instead of blocking the refresh.
+pub async fn with_retry<T, F, Fut>(policy: &RetryPolicy, mut operation: F) -> Result<T, AppError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, AppError>>,
+{
+    let max_attempts = policy.max_attempts.max(1);
+    let mut attempt = 1;
+
+    loop {
+        let error = match operation().await {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        };
+
+        if !error.is_retryable() {
+            return Err(error);
+        }
+
+        if attempt >= max_attempts {
+            return Err(AppError::RetriesExhausted {
+                attempts: attempt,
+                last: Box::new(error),
+            });
+        }
+
+        let delay = match error.retry_after() {
+            Some(wait) if wait > Duration::from_millis(policy.max_backoff_ms) => {
+                return Err(AppError::RetriesExhausted {
+                    attempts: attempt,
+                    last: Box::new(error),
+                });
+            }
+            Some(wait) => wait,
+            None => policy.delay_for(attempt),
+        };
+
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
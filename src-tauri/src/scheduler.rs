@@ -0,0 +1,328 @@
+use crate::api::UsageClient;
+use crate::commands::AppState;
+use crate::error::AppError;
+use crate::forecast;
+use crate::types::{AllUsageData, Config, TimeWindow, UsageAlert};
+use futures::future::join_all;
+use std::sync::Mutex;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+
+/// Background refresh loop owned by `AppState`
+///
+/// Runs on the Tauri async runtime so the tray keeps updating even when
+/// the webview is hidden, throttled or was never loaded.
+pub struct Scheduler {
+    handle: Mutex<Option<JoinHandle<()>>>,
+    interval_minutes: Mutex<u64>,
+    /// One-off refresh planned for a quota reset, with its time in Unix seconds
+    reset_refresh: Mutex<Option<(i64, JoinHandle<()>)>>,
+}
+
+/// Delay after a quota reset before refreshing, so the API has caught up
+const RESET_GRACE_SECS: i64 = 5;
+
+impl Scheduler {
+    /// Create an idle scheduler; call `start` once the app handle is available
+    pub fn new() -> Self {
+        Self {
+            handle: Mutex::new(None),
+            interval_minutes: Mutex::new(0),
+            reset_refresh: Mutex::new(None),
+        }
+    }
+
+    /// Start (or restart) the refresh loop with the given interval
+    ///
+    /// The first refresh runs immediately, then every `interval_minutes`.
+    pub fn start<R: Runtime>(&self, app: AppHandle<R>, interval_minutes: u64) {
+        self.stop();
+
+        // Guard against a zero interval, which would spin the loop
+        let interval_minutes = interval_minutes.max(1);
+        let period = tokio::time::Duration::from_secs(interval_minutes * 60);
+
+        let handle = tauri::async_runtime::spawn(async move {
+            let mut ticker = tokio::time::interval(period);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                ticker.tick().await;
+                if let Err(e) = refresh_usage(&app, None).await {
+                    tracing::warn!(error = %e, "Scheduled refresh failed");
+                }
+            }
+        });
+
+        *self.handle.lock().unwrap() = Some(handle);
+        *self.interval_minutes.lock().unwrap() = interval_minutes;
+    }
+
+    /// Restart the loop only if the interval differs from the running one
+    pub fn restart_if_changed<R: Runtime>(&self, app: AppHandle<R>, interval_minutes: u64) {
+        let running = self.handle.lock().unwrap().is_some();
+        let current = *self.interval_minutes.lock().unwrap();
+
+        if !running || current != interval_minutes.max(1) {
+            self.start(app, interval_minutes);
+        }
+    }
+
+    /// Refresh once right after the quota resets at `reset_at` (Unix seconds)
+    ///
+    /// Replaces an earlier plan for a different time; `None` cancels it.
+    pub fn schedule_reset_refresh<R: Runtime>(&self, app: AppHandle<R>, reset_at: Option<i64>) {
+        let mut planned = self.reset_refresh.lock().unwrap();
+        if planned.as_ref().map(|(at, _)| *at) == reset_at {
+            return;
+        }
+        if let Some((_, handle)) = planned.take() {
+            handle.abort();
+        }
+
+        let Some(reset_at) = reset_at else {
+            return;
+        };
+        let delay = (reset_at + RESET_GRACE_SECS - chrono::Local::now().timestamp()).max(0) as u64;
+
+        let handle = tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(tokio::time::Duration::from_secs(delay)).await;
+
+            // Clear this plan first: the refresh plans the next reset, which
+            // would otherwise abort this very task
+            app.state::<AppState>().scheduler.clear_reset_refresh(reset_at);
+
+            if let Err(e) = refresh_usage(&app, None).await {
+                tracing::warn!(error = %e, "Refresh at quota reset failed");
+            }
+        });
+        *planned = Some((reset_at, handle));
+    }
+
+    /// Forget the plan for `reset_at` without aborting its task
+    fn clear_reset_refresh(&self, reset_at: i64) {
+        let mut planned = self.reset_refresh.lock().unwrap();
+        if planned.as_ref().is_some_and(|(at, _)| *at == reset_at) {
+            planned.take();
+        }
+    }
+
+    /// Stop the refresh loop if it is running
+    pub fn stop(&self) {
+        if let Some(handle) = self.handle.lock().unwrap().take() {
+            handle.abort();
+        }
+    }
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Fetch usage for every profile, store it in `AppState`, update the tray and notify the webview
+///
+/// Shared by the scheduler, the tray "Refresh Now" item and the `get_usage_data` command.
+/// Quota limits are always current; `window` only applies to model and tool usage.
+/// A given `window` becomes the selected one; `None` reuses the last selection.
+/// Returns the active profile's data.
+pub async fn refresh_usage<R: Runtime>(
+    app: &AppHandle<R>,
+    window: Option<TimeWindow>,
+) -> Result<AllUsageData, AppError> {
+    let state = app.state::<AppState>();
+    let window = {
+        let mut selected = state.time_window.lock().unwrap();
+        if let Some(window) = window {
+            *selected = window;
+        }
+        selected.clone()
+    };
+    let config = state.config.lock().unwrap().clone();
+    let profiles = config.all_profiles();
+
+    // Profiles are independent accounts, so fetch them all at once
+    let results = join_all(
+        profiles
+            .iter()
+            .map(|profile| refresh_profile(app, config.for_profile(profile), window.clone())),
+    )
+    .await;
+
+    if let Err(e) = crate::update_tray(app) {
+        tracing::warn!(error = %e, "Failed to update tray");
+    }
+
+    // Pick up fresh quota as soon as the earliest token window resets
+    let now = chrono::Local::now().timestamp();
+    let next_reset = state
+        .usage_by_profile
+        .lock()
+        .unwrap()
+        .values()
+        .filter_map(forecast::token_reset_at)
+        .filter(|at| *at > now)
+        .min();
+    state.scheduler.schedule_reset_refresh(app.clone(), next_reset);
+
+    let active = profiles
+        .iter()
+        .zip(results)
+        .find(|(profile, _)| profile.name == config.active_profile)
+        .map(|(_, result)| result)
+        .unwrap_or(Err(AppError::NotConfigured))?;
+
+    // Push the active profile's data to the webview
+    let _ = app.emit("usage-data-updated", &active);
+
+    Ok(active)
+}
+
+/// Fetch usage for a single profile right away, e.g. after it was added or edited
+///
+/// Uses the selected window and updates the tray, and the webview if `name` is active.
+pub async fn refresh_single_profile<R: Runtime>(app: &AppHandle<R>, name: &str) -> Result<AllUsageData, AppError> {
+    let state = app.state::<AppState>();
+    let window = state.time_window.lock().unwrap().clone();
+    let config = state.config.lock().unwrap().clone();
+    let profile = config
+        .find_profile(name)
+        .ok_or_else(|| AppError::InvalidInput(format!("Unknown profile: {}", name)))?;
+
+    let result = refresh_profile(app, config.for_profile(&profile), window).await;
+
+    if let Err(e) = crate::update_tray(app) {
+        tracing::warn!(error = %e, "Failed to update tray");
+    }
+
+    let data = result?;
+    if name == config.active_profile {
+        let _ = app.emit("usage-data-updated", &data);
+    }
+    Ok(data)
+}
+
+/// Fetch and store usage for a single profile
+///
+/// `config` is the profile-specific config from `Config::for_profile`.
+async fn refresh_profile<R: Runtime>(
+    app: &AppHandle<R>,
+    config: Config,
+    window: TimeWindow,
+) -> Result<AllUsageData, AppError> {
+    let state = app.state::<AppState>();
+    let profile = config.active_profile.clone();
+
+    if config.auth_token.is_empty() {
+        return Err(record_error(app, &profile, AppError::NotConfigured));
+    }
+
+    let retention_days = config.history_retention_days;
+    let alert_rules = config.alert_rules.clone();
+    let notify_quota_available = config.notify_quota_available;
+    let client = UsageClient::new(config);
+
+    // Each section may fail independently
+    let results = client.fetch_all(&window).await;
+    let now = chrono::Local::now().timestamp();
+    let previous = state.usage_by_profile.lock().unwrap().get(&profile).cloned();
+    let data = match results.into_usage_data(previous.as_ref(), &profile, window, now) {
+        Ok(data) => data,
+        Err(e) => return Err(record_error(app, &profile, e)),
+    };
+
+    // Store in state for tray access
+    state.usage_by_profile.lock().unwrap().insert(profile.clone(), data.clone());
+    state.errors_by_profile.lock().unwrap().remove(&profile);
+
+    // Keep every snapshot on disk for trend queries
+    if let Err(e) = state.history.append(&data) {
+        tracing::warn!(error = %e, "Failed to record usage history");
+    }
+    if let Err(e) = state.history.prune(retention_days) {
+        tracing::warn!(error = %e, "Failed to prune usage history");
+    }
+
+    // Project when the token quota runs out from the recent snapshots
+    let recent = state
+        .history
+        .query(Some(now - forecast::LOOKBACK_SECS), None, Some(&profile))
+        .unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "Failed to read usage history");
+            Vec::new()
+        });
+    let forecast = forecast::forecast_token_quota(&data, &recent, now);
+    let mut forecasts = state.forecasts_by_profile.lock().unwrap();
+    match &forecast {
+        Some(forecast) => forecasts.insert(profile.clone(), forecast.clone()),
+        None => forecasts.remove(&profile),
+    };
+    drop(forecasts);
+
+    // Let the user know when an exhausted token quota has reset
+    let was_exhausted = previous
+        .as_ref()
+        .filter(|p| p.status.quota.error.is_none())
+        .and_then(forecast::token_limit)
+        .is_some_and(forecast::is_exhausted);
+    let available = data.status.quota.error.is_none()
+        && forecast::token_limit(&data).is_some_and(|l| !forecast::is_exhausted(l));
+    if was_exhausted && available {
+        let _ = app.emit("quota-available", &profile);
+        if notify_quota_available {
+            let open_app = app.clone();
+            if let Err(e) = state.notifications.notify_quota_available(&profile, now, move || {
+                crate::show_main_window(&open_app)
+            }) {
+                tracing::warn!(error = %e, "Failed to show notification");
+            }
+        }
+    }
+
+    // Evaluate alert rules, but never against stale quota values
+    if data.status.quota.error.is_none() {
+        match state.alerts.evaluate(&alert_rules, &data, forecast.as_ref(), now) {
+            Ok(alerts) => {
+                for alert in alerts {
+                    let _ = app.emit("usage-alert", &alert);
+                    let open_app = app.clone();
+                    if let Err(e) = state.notifications.notify_alert(&alert, now, move || {
+                        crate::show_main_window(&open_app)
+                    }) {
+                        tracing::warn!(error = %e, "Failed to show notification");
+                    }
+                    send_webhooks(app, alert);
+                }
+            }
+            Err(e) => tracing::warn!(error = %e, "Failed to evaluate alert rules"),
+        }
+    }
+
+    Ok(data)
+}
+
+/// Deliver an alert to the configured webhooks without holding up the refresh
+fn send_webhooks<R: Runtime>(app: &AppHandle<R>, alert: UsageAlert) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+        let (webhooks, policy) = {
+            let config = state.config.lock().unwrap();
+            (config.webhooks.clone(), config.retry.clone())
+        };
+
+        for delivery in state.webhook_dispatcher.dispatch(&webhooks, &alert, &policy).await {
+            if let Some(error) = delivery.error {
+                tracing::warn!(webhook = %delivery.webhook_id, error = %error, "Webhook delivery failed");
+            }
+        }
+    });
+}
+
+/// Remember a failed refresh so the tray can reflect it, then hand the error back
+fn record_error<R: Runtime>(app: &AppHandle<R>, profile: &str, error: AppError) -> AppError {
+    let state = app.state::<AppState>();
+    state.errors_by_profile.lock().unwrap().insert(profile.to_string(), error.clone());
+    error
+}
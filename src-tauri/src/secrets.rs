@@ -0,0 +1,298 @@
+use crate::error::AppError;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Keyring service name under which tokens are stored
+const KEYRING_SERVICE: &str = "zai-usage-monitor";
+
+/// Environment variable holding the passphrase for the encrypted file backend
+pub const PASSPHRASE_ENV: &str = "ZAI_SECRETS_PASSPHRASE";
+
+/// Length in bytes of an AES-GCM nonce
+const NONCE_LEN: usize = 12;
+
+/// Storage backend for API tokens
+///
+/// config.json only keeps a reference such as `keyring:profile:default`;
+/// the token itself lives in the selected backend.
+pub trait SecretStore {
+    /// Read a secret, or `None` if nothing is stored under `key`
+    fn get(&self, key: &str) -> Result<Option<String>, AppError>;
+    /// Store or replace a secret
+    fn set(&self, key: &str, secret: &str) -> Result<(), AppError>;
+    /// Remove a secret; removing a missing key is not an error
+    fn delete(&self, key: &str) -> Result<(), AppError>;
+}
+
+/// Which backend new secrets are written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretBackend {
+    /// macOS Keychain, Windows Credential Manager or the Secret Service on Linux
+    #[default]
+    Keyring,
+    /// `secrets.json` next to config.json, encrypted with a key derived from
+    /// the passphrase in `ZAI_SECRETS_PASSPHRASE`
+    EncryptedFile,
+}
+
+impl SecretBackend {
+    /// Prefix used in secret references
+    fn prefix(self) -> &'static str {
+        match self {
+            SecretBackend::Keyring => "keyring",
+            SecretBackend::EncryptedFile => "file",
+        }
+    }
+
+    /// Open the store for this backend
+    pub fn open(self) -> Result<Box<dyn SecretStore>, AppError> {
+        match self {
+            SecretBackend::Keyring => Ok(Box::new(KeyringStore::new(KEYRING_SERVICE))),
+            SecretBackend::EncryptedFile => {
+                let passphrase = std::env::var(PASSPHRASE_ENV).map_err(|_| {
+                    AppError::Config(format!(
+                        "The encrypted file backend needs a passphrase in {}",
+                        PASSPHRASE_ENV
+                    ))
+                })?;
+                Ok(Box::new(EncryptedFileStore::new(
+                    crate::config::get_data_dir().join("secrets.json"),
+                    passphrase,
+                )))
+            }
+        }
+    }
+
+    /// Build a reference to `key` in this backend, e.g. `keyring:profile:default`
+    pub fn reference(self, key: &str) -> String {
+        format!("{}:{}", self.prefix(), key)
+    }
+
+    /// Split a reference into its backend and key
+    pub fn parse_reference(reference: &str) -> Result<(SecretBackend, &str), AppError> {
+        let (prefix, key) = reference
+            .split_once(':')
+            .ok_or_else(|| AppError::Config(format!("Invalid secret reference: {}", reference)))?;
+
+        let backend = match prefix {
+            "keyring" => SecretBackend::Keyring,
+            "file" => SecretBackend::EncryptedFile,
+            _ => return Err(AppError::Config(format!("Unknown secret backend: {}", prefix))),
+        };
+        Ok((backend, key))
+    }
+}
+
+/// Read the secret a reference points to
+pub fn resolve_reference(reference: &str) -> Result<Option<String>, AppError> {
+    let (backend, key) = SecretBackend::parse_reference(reference)?;
+    backend.open()?.get(key)
+}
+
+/// Delete the secret a reference points to
+pub fn delete_reference(reference: &str) -> Result<(), AppError> {
+    let (backend, key) = SecretBackend::parse_reference(reference)?;
+    backend.open()?.delete(key)
+}
+
+/// Secrets kept in the operating system's credential store
+pub struct KeyringStore {
+    service: String,
+}
+
+impl KeyringStore {
+    pub fn new(service: &str) -> Self {
+        Self {
+            service: service.to_string(),
+        }
+    }
+
+    fn entry(&self, key: &str) -> Result<keyring::Entry, AppError> {
+        keyring::Entry::new(&self.service, key)
+            .map_err(|e| AppError::Storage(format!("Failed to open keyring entry: {}", e)))
+    }
+}
+
+impl SecretStore for KeyringStore {
+    fn get(&self, key: &str) -> Result<Option<String>, AppError> {
+        match self.entry(key)?.get_password() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(AppError::Storage(format!("Failed to read from keyring: {}", e))),
+        }
+    }
+
+    fn set(&self, key: &str, secret: &str) -> Result<(), AppError> {
+        self.entry(key)?
+            .set_password(secret)
+            .map_err(|e| AppError::Storage(format!("Failed to write to keyring: {}", e)))
+    }
+
+    fn delete(&self, key: &str) -> Result<(), AppError> {
+        match self.entry(key)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(AppError::Storage(format!("Failed to delete from keyring: {}", e))),
+        }
+    }
+}
+
+/// On-disk layout of the encrypted secrets file
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct EncryptedFile {
+    /// Base64 salt for the Argon2 key derivation
+    salt: String,
+    /// Base64 nonce and ciphertext per key
+    entries: BTreeMap<String, EncryptedEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EncryptedEntry {
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Secrets kept in a local JSON file, each encrypted with AES-256-GCM
+/// under a key derived from a passphrase with Argon2id
+pub struct EncryptedFileStore {
+    path: PathBuf,
+    passphrase: String,
+}
+
+impl EncryptedFileStore {
+    pub fn new(path: PathBuf, passphrase: String) -> Self {
+        Self { path, passphrase }
+    }
+
+    fn read(&self) -> Result<EncryptedFile, AppError> {
+        if !self.path.exists() {
+            let salt: [u8; 16] = rand::random();
+            return Ok(EncryptedFile {
+                salt: BASE64.encode(salt),
+                entries: BTreeMap::new(),
+            });
+        }
+
+        let contents = fs::read_to_string(&self.path)
+            .map_err(|e| AppError::Storage(format!("Failed to read secrets: {}", e)))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| AppError::Storage(format!("Failed to parse secrets: {}", e)))
+    }
+
+    fn write(&self, file: &EncryptedFile) -> Result<(), AppError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| AppError::Storage(format!("Failed to create secrets directory: {}", e)))?;
+        }
+
+        let contents = serde_json::to_string_pretty(file)
+            .map_err(|e| AppError::Storage(format!("Failed to serialize secrets: {}", e)))?;
+
+        // Readable by the owner only, including files written by older versions
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+            options.mode(0o600);
+            if self.path.exists() {
+                fs::set_permissions(&self.path, fs::Permissions::from_mode(0o600))
+                    .map_err(|e| AppError::Storage(format!("Failed to restrict secrets permissions: {}", e)))?;
+            }
+        }
+
+        options
+            .open(&self.path)
+            .and_then(|mut f| f.write_all(contents.as_bytes()))
+            .map_err(|e| AppError::Storage(format!("Failed to write secrets: {}", e)))
+    }
+
+    fn cipher(&self, salt: &str) -> Result<Aes256Gcm, AppError> {
+        let salt = BASE64
+            .decode(salt)
+            .map_err(|e| AppError::Storage(format!("Invalid secrets salt: {}", e)))?;
+
+        let mut key = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(self.passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|e| AppError::Storage(format!("Failed to derive key: {}", e)))?;
+
+        Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+    }
+}
+
+impl SecretStore for EncryptedFileStore {
+    fn get(&self, key: &str) -> Result<Option<String>, AppError> {
+        let file = self.read()?;
+        let Some(entry) = file.entries.get(key) else {
+            return Ok(None);
+        };
+
+        let plaintext = decrypt(&self.cipher(&file.salt)?, entry)?;
+        String::from_utf8(plaintext)
+            .map(Some)
+            .map_err(|e| AppError::Storage(format!("Secret is not valid UTF-8: {}", e)))
+    }
+
+    fn set(&self, key: &str, secret: &str) -> Result<(), AppError> {
+        let mut file = self.read()?;
+        let cipher = self.cipher(&file.salt)?;
+
+        // A different passphrase would leave entries under two keys in one file
+        if let Some(existing) = file.entries.values().next() {
+            decrypt(&cipher, existing)?;
+        }
+
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, secret.as_bytes())
+            .map_err(|_| AppError::Storage("Failed to encrypt secret".to_string()))?;
+
+        file.entries.insert(
+            key.to_string(),
+            EncryptedEntry {
+                nonce: BASE64.encode(nonce),
+                ciphertext: BASE64.encode(ciphertext),
+            },
+        );
+        self.write(&file)
+    }
+
+    fn delete(&self, key: &str) -> Result<(), AppError> {
+        let mut file = self.read()?;
+        if file.entries.remove(key).is_some() {
+            self.write(&file)?;
+        }
+        Ok(())
+    }
+}
+
+/// Decrypt one entry, failing on a corrupt entry or a key from another passphrase
+fn decrypt(cipher: &Aes256Gcm, entry: &EncryptedEntry) -> Result<Vec<u8>, AppError> {
+    let decode = |value: &str| {
+        BASE64
+            .decode(value)
+            .map_err(|e| AppError::Storage(format!("Corrupt secret entry: {}", e)))
+    };
+    let nonce = decode(&entry.nonce)?;
+    let ciphertext = decode(&entry.ciphertext)?;
+    if nonce.len() != NONCE_LEN {
+        return Err(AppError::Storage(format!(
+            "Corrupt secret entry: nonce is {} bytes, expected {}",
+            nonce.len(),
+            NONCE_LEN
+        )));
+    }
+
+    cipher
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+        .map_err(|_| AppError::Storage("Failed to decrypt secret: wrong passphrase?".to_string()))
+}
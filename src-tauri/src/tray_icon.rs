@@ -0,0 +1,86 @@
+#[cfg(feature = "gui")]
+use tauri::image::Image;
+
+/// Width and height of the rendered icon in pixels
+pub const ICON_SIZE: u32 = 32;
+
+/// Ring thickness as a fraction of the icon size
+const RING_WIDTH: f64 = 0.2;
+
+/// Subsamples per pixel along each axis, for anti-aliased edges
+const SUBSAMPLES: u32 = 4;
+
+/// Unfilled part of the ring
+const TRACK: [u8; 4] = [128, 128, 128, 90];
+
+/// Ring color for a percentage, matching the bands used in the UI
+///
+/// Green below 50%, yellow below 70%, amber below 90%, red from 90%.
+pub fn band_color(percentage: f64) -> [u8; 4] {
+    if percentage >= 90.0 {
+        [0xEF, 0x44, 0x44, 0xFF]
+    } else if percentage >= 70.0 {
+        [0xF5, 0x9E, 0x0B, 0xFF]
+    } else if percentage >= 50.0 {
+        [0xEA, 0xB3, 0x08, 0xFF]
+    } else {
+        [0x10, 0xB9, 0x81, 0xFF]
+    }
+}
+
+/// Render a progress ring filled clockwise from 12 o'clock to `percentage`
+///
+/// `None` draws only the track, for when there is no usage to show.
+/// Returns RGBA pixels of `size` x `size`.
+pub fn render_ring(percentage: Option<f64>, size: u32) -> Vec<u8> {
+    let fraction = percentage.map(|p| (p / 100.0).clamp(0.0, 1.0)).unwrap_or(0.0);
+    let fill = band_color(percentage.unwrap_or(0.0));
+
+    let center = size as f64 / 2.0;
+    let outer = center;
+    let inner = center - size as f64 * RING_WIDTH;
+    let samples = (SUBSAMPLES * SUBSAMPLES) as f64;
+
+    let mut pixels = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            // Accumulate premultiplied color over the subsamples
+            let mut sum = [0.0f64; 4];
+            for sy in 0..SUBSAMPLES {
+                for sx in 0..SUBSAMPLES {
+                    let px = x as f64 + (sx as f64 + 0.5) / SUBSAMPLES as f64 - center;
+                    let py = y as f64 + (sy as f64 + 0.5) / SUBSAMPLES as f64 - center;
+                    let radius = (px * px + py * py).sqrt();
+                    if radius < inner || radius > outer {
+                        continue;
+                    }
+
+                    // Angle clockwise from 12 o'clock, as a fraction of a full turn
+                    let turn = (px.atan2(-py) / std::f64::consts::TAU).rem_euclid(1.0);
+                    let color = if turn < fraction { fill } else { TRACK };
+                    let alpha = color[3] as f64 / 255.0;
+                    for channel in 0..3 {
+                        sum[channel] += color[channel] as f64 * alpha;
+                    }
+                    sum[3] += alpha;
+                }
+            }
+
+            if sum[3] == 0.0 {
+                pixels.extend_from_slice(&[0, 0, 0, 0]);
+                continue;
+            }
+            for channel in sum.iter().take(3) {
+                pixels.push((channel / sum[3]).round() as u8);
+            }
+            pixels.push((sum[3] / samples * 255.0).round() as u8);
+        }
+    }
+    pixels
+}
+
+/// Tray icon showing the token quota percentage as a ring
+#[cfg(feature = "gui")]
+pub fn quota_icon(percentage: Option<f64>) -> Image<'static> {
+    Image::new_owned(render_ring(percentage, ICON_SIZE), ICON_SIZE, ICON_SIZE)
+}
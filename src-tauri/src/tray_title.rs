@@ -0,0 +1,268 @@
+use crate::error::AppError;
+use crate::forecast::format_duration;
+use crate::types::{AllUsageData, QuotaForecast, QuotaKind, QuotaLimit};
+
+/// Title used when no template is configured
+pub const DEFAULT_TEMPLATE: &str = "🆉 T:{token.pct}% M:{mcp.pct}%{if stale} ⚠{end}";
+
+/// Placeholders a template may use
+///
+/// Each quota (`token`, `mcp`) provides `pct`, `remaining`, `used` and `reset`
+/// (time until it resets, e.g. "1h 5m").
+const KEYS: &[&str] = &[
+    "token.pct",
+    "token.remaining",
+    "token.used",
+    "token.reset",
+    "mcp.pct",
+    "mcp.remaining",
+    "mcp.used",
+    "mcp.reset",
+    "forecast",
+    "profile",
+    "stale",
+];
+
+/// A parsed tray title template
+///
+/// Placeholders are written `{token.pct}`; conditional segments are written
+/// `{if mcp.pct > 50} M:{mcp.pct}%{end}` (operators `>`, `>=`, `<`, `<=`, `==`,
+/// `!=`) or `{if forecast}...{end}` to test that a value is present.
+/// `{{` and `}}` produce literal braces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrayTemplate {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Text(String),
+    Value(&'static str),
+    If { condition: Condition, body: Vec<Segment> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Condition {
+    Present(&'static str),
+    Compare { key: &'static str, op: Op, number: f64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Eq,
+    Ne,
+}
+
+/// Value of a placeholder
+enum Value {
+    Number(f64),
+    Text(String),
+    Missing,
+}
+
+impl Value {
+    fn render(&self) -> String {
+        match self {
+            Value::Number(n) => format!("{:.0}", n),
+            Value::Text(text) => text.clone(),
+            Value::Missing => "-".to_string(),
+        }
+    }
+}
+
+/// Data a template is rendered from
+pub struct TitleContext<'a> {
+    pub data: &'a AllUsageData,
+    /// Token and MCP percentages to show instead of the active profile's, e.g. the worst case
+    pub percentages: (f64, f64),
+    pub forecast: Option<&'a QuotaForecast>,
+    /// Unix seconds
+    pub now: i64,
+}
+
+impl TrayTemplate {
+    /// Parse a template, rejecting unknown placeholders and unbalanced blocks
+    pub fn parse(template: &str) -> Result<Self, AppError> {
+        let mut stack: Vec<(Condition, Vec<Segment>)> = Vec::new();
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut rest = template;
+
+        while let Some(c) = rest.chars().next() {
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                text.push(c);
+                rest = &rest[2..];
+                continue;
+            }
+            if c == '}' {
+                return Err(invalid("unmatched '}'"));
+            }
+            if c != '{' {
+                text.push(c);
+                rest = &rest[c.len_utf8()..];
+                continue;
+            }
+
+            let end = rest.find('}').ok_or_else(|| invalid("unclosed '{'"))?;
+            let tag = rest[1..end].trim();
+            rest = &rest[end + 1..];
+
+            let current = match stack.last_mut() {
+                Some((_, body)) => body,
+                None => &mut segments,
+            };
+            if !text.is_empty() {
+                current.push(Segment::Text(std::mem::take(&mut text)));
+            }
+
+            if let Some(condition) = tag.strip_prefix("if ") {
+                stack.push((parse_condition(condition.trim())?, Vec::new()));
+            } else if tag == "end" {
+                let (condition, body) = stack.pop().ok_or_else(|| invalid("{end} without {if}"))?;
+                let parent = match stack.last_mut() {
+                    Some((_, body)) => body,
+                    None => &mut segments,
+                };
+                parent.push(Segment::If { condition, body });
+            } else {
+                current.push(Segment::Value(parse_key(tag)?));
+            }
+        }
+
+        if !stack.is_empty() {
+            return Err(invalid("{if} without {end}"));
+        }
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+
+        Ok(Self { segments })
+    }
+
+    /// Render the template for the given usage data
+    pub fn render(&self, context: &TitleContext) -> String {
+        let mut output = String::new();
+        render_segments(&self.segments, context, &mut output);
+        output
+    }
+}
+
+fn invalid(reason: &str) -> AppError {
+    AppError::InvalidInput(format!("Invalid tray title template: {}", reason))
+}
+
+fn parse_key(key: &str) -> Result<&'static str, AppError> {
+    KEYS.iter().copied().find(|k| *k == key).ok_or_else(|| {
+        invalid(&format!(
+            "unknown placeholder {{{}}}; available: {}",
+            key,
+            KEYS.join(", ")
+        ))
+    })
+}
+
+fn parse_condition(condition: &str) -> Result<Condition, AppError> {
+    // Two-character operators first so ">=" isn't read as ">"
+    let operators = [
+        (">=", Op::Ge),
+        ("<=", Op::Le),
+        ("==", Op::Eq),
+        ("!=", Op::Ne),
+        (">", Op::Gt),
+        ("<", Op::Lt),
+    ];
+
+    for (symbol, op) in operators {
+        if let Some((key, number)) = condition.split_once(symbol) {
+            let key = parse_key(key.trim())?;
+            let number = number
+                .trim()
+                .parse()
+                .map_err(|_| invalid(&format!("expected a number in {{if {}}}", condition)))?;
+            return Ok(Condition::Compare { key, op, number });
+        }
+    }
+
+    Ok(Condition::Present(parse_key(condition)?))
+}
+
+fn render_segments(segments: &[Segment], context: &TitleContext, output: &mut String) {
+    for segment in segments {
+        match segment {
+            Segment::Text(text) => output.push_str(text),
+            Segment::Value(key) => output.push_str(&value(key, context).render()),
+            Segment::If { condition, body } => {
+                if evaluate(condition, context) {
+                    render_segments(body, context, output);
+                }
+            }
+        }
+    }
+}
+
+fn evaluate(condition: &Condition, context: &TitleContext) -> bool {
+    match condition {
+        Condition::Present(key) => match value(key, context) {
+            Value::Number(n) => n != 0.0,
+            Value::Text(text) => !text.is_empty(),
+            Value::Missing => false,
+        },
+        Condition::Compare { key, op, number } => {
+            let Value::Number(value) = value(key, context) else {
+                return false;
+            };
+            match op {
+                Op::Gt => value > *number,
+                Op::Ge => value >= *number,
+                Op::Lt => value < *number,
+                Op::Le => value <= *number,
+                Op::Eq => value == *number,
+                Op::Ne => value != *number,
+            }
+        }
+    }
+}
+
+fn value(key: &str, context: &TitleContext) -> Value {
+    let data = context.data;
+
+    match key.split_once('.') {
+        Some((quota, field)) => {
+            let (limit, pct) = match quota {
+                "token" => (data.quota(&QuotaKind::Tokens), context.percentages.0),
+                _ => (data.quota(&QuotaKind::Time), context.percentages.1),
+            };
+            quota_value(limit, pct, field, context.now)
+        }
+        None => match key {
+            "forecast" => context
+                .forecast
+                .filter(|f| f.exhausted_before_reset)
+                .and_then(|f| f.exhausted_at)
+                .map(|at| Value::Text(format_duration(at - context.now)))
+                .unwrap_or(Value::Text(String::new())),
+            "profile" => Value::Text(data.profile.clone()),
+            "stale" => Value::Text(if data.status.quota.error.is_some() { "stale" } else { "" }.to_string()),
+            _ => Value::Missing,
+        },
+    }
+}
+
+fn quota_value(limit: Option<&QuotaLimit>, pct: f64, field: &str, now: i64) -> Value {
+    let number = |n: Option<i64>| n.map(|n| Value::Number(n as f64)).unwrap_or(Value::Missing);
+
+    match field {
+        "pct" => Value::Number(pct),
+        "remaining" => number(limit.and_then(|l| l.remaining)),
+        "used" => number(limit.and_then(|l| l.current_value)),
+        "reset" => limit
+            .and_then(|l| l.resets_at)
+            .map(|at| Value::Text(format_duration(at - now)))
+            .unwrap_or(Value::Missing),
+        _ => Value::Missing,
+    }
+}
@@ -1,12 +1,54 @@
+use crate::error::AppError;
+use crate::secrets::SecretBackend;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
-/// Model usage response from API
+/// `code` of a successful API response
+pub const API_SUCCESS_CODE: i64 = 200;
+
+/// Envelope wrapping every monitor API response
+///
+/// Error responses may omit `data` or send it as `null`, so it is optional
+/// here and only required once `check` has passed.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ModelUsageResponse {
-    pub code: i64,
+pub struct ApiResponse<T> {
+    #[serde(default)]
+    pub code: Option<i64>,
+    #[serde(default)]
     pub msg: String,
-    pub data: ModelUsageData,
-    pub success: bool,
+    #[serde(default)]
+    pub success: Option<bool>,
+    pub data: Option<T>,
+}
+
+impl<T> ApiResponse<T> {
+    /// Fail with `AppError::Api` if the server reported a business failure
+    ///
+    /// A response fails when `success` is false or `code` is not 200; either
+    /// field may be missing.
+    pub fn check(&self) -> Result<(), AppError> {
+        let succeeded = self.success != Some(false) && self.code.is_none_or(|code| code == API_SUCCESS_CODE);
+        if succeeded {
+            return Ok(());
+        }
+
+        let msg = if self.msg.trim().is_empty() {
+            "the request was not successful".to_string()
+        } else {
+            self.msg.trim().to_string()
+        };
+        Err(AppError::Api {
+            code: self.code,
+            msg,
+        })
+    }
+
+    /// The payload of a successful response
+    pub fn into_data(self) -> Result<T, AppError> {
+        self.check()?;
+        self.data
+            .ok_or_else(|| AppError::Parse("Successful response has no data".to_string()))
+    }
 }
 
 /// Model usage data wrapper (time-series data)
@@ -39,15 +81,6 @@ pub struct ModelUsageItem {
     pub request_count: i64,
 }
 
-/// Tool usage response from API
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ToolUsageResponse {
-    pub code: i64,
-    pub msg: String,
-    pub data: ToolUsageData,
-    pub success: bool,
-}
-
 /// Tool usage data wrapper
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolUsageData {
@@ -88,15 +121,6 @@ pub struct ToolUsageItem {
     pub usage_count: i64,
 }
 
-/// Quota limit response from API
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct QuotaLimitResponse {
-    pub code: i64,
-    pub msg: String,
-    pub data: QuotaLimitData,
-    pub success: bool,
-}
-
 /// Quota limit data wrapper
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QuotaLimitData {
@@ -104,10 +128,12 @@ pub struct QuotaLimitData {
 }
 
 /// Individual quota limit
+///
+/// Display labels are built by `labels::quota_label`, not stored here.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QuotaLimit {
     #[serde(rename = "type")]
-    pub type_field: String,
+    pub kind: QuotaKind,
     pub unit: i64,
     pub number: i64,
     #[serde(default)]
@@ -119,14 +145,184 @@ pub struct QuotaLimit {
     pub percentage: f64,
     #[serde(rename = "usageDetails", default)]
     pub usage_details: Option<Vec<UsageDetail>>,
+    /// Unix milliseconds, as sent by the API
     #[serde(rename = "nextResetTime", default)]
     pub next_reset_time: Option<i64>,
+    /// `unit` and `number` decoded, filled in by `QuotaLimit::decode`
+    #[serde(default)]
+    pub window: Option<QuotaWindow>,
+    /// Start of the current window in Unix seconds, filled in by `QuotaLimit::decode`
+    #[serde(rename = "windowStart", default)]
+    pub window_start: Option<i64>,
+    /// `next_reset_time` in Unix seconds, filled in by `QuotaLimit::decode`
+    #[serde(rename = "resetsAt", default)]
+    pub resets_at: Option<i64>,
+}
+
+impl QuotaLimit {
+    /// Derive `window`, `window_start` and `resets_at` from the raw API fields
+    pub fn decode(&mut self) {
+        let window = QuotaWindow::from_raw(self.unit, self.number);
+        self.resets_at = self.next_reset_time.map(|ms| ms.div_euclid(1000));
+        self.window_start = self.resets_at.and_then(|reset| window.start_before(reset));
+        self.window = Some(window);
+    }
+}
+
+/// Unit of a quota window, from the API's numeric `unit`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowUnit {
+    Minute,
+    Hour,
+    Day,
+    Month,
+    /// A unit code this version doesn't know
+    Unknown,
+}
+
+/// Length of a quota window: `number` times `unit`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuotaWindow {
+    pub unit: WindowUnit,
+    pub number: i64,
+}
+
+impl QuotaWindow {
+    /// Decode the API's `unit` (2 minute, 3 hour, 4 day, 5 month) and `number`
+    pub fn from_raw(unit: i64, number: i64) -> Self {
+        let unit = match unit {
+            2 => WindowUnit::Minute,
+            3 => WindowUnit::Hour,
+            4 => WindowUnit::Day,
+            5 => WindowUnit::Month,
+            _ => WindowUnit::Unknown,
+        };
+        Self { unit, number }
+    }
+
+    /// Approximate length in seconds, counting a month as 30 days
+    pub fn approx_secs(&self) -> Option<i64> {
+        let unit_secs = match self.unit {
+            WindowUnit::Minute => 60,
+            WindowUnit::Hour => 60 * 60,
+            WindowUnit::Day => 24 * 60 * 60,
+            WindowUnit::Month => 30 * 24 * 60 * 60,
+            WindowUnit::Unknown => return None,
+        };
+        Some(unit_secs * self.number)
+    }
+
+    /// Start of the window ending at `end` (Unix seconds)
+    ///
+    /// Months are calendar months in UTC. `None` for unknown units.
+    pub fn start_before(&self, end: i64) -> Option<i64> {
+        if self.unit != WindowUnit::Month {
+            return self.approx_secs().map(|secs| end - secs);
+        }
+
+        let months = chrono::Months::new(u32::try_from(self.number).ok()?);
+        chrono::DateTime::from_timestamp(end, 0)?
+            .checked_sub_months(months)
+            .map(|start| start.timestamp())
+    }
+}
+
+/// Result of probing one monitor endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndpointProbe {
+    /// "quota", "model_usage" or "tool_usage"
+    pub endpoint: String,
+    pub url: String,
+    /// Whether the endpoint answered with a response that could be parsed
+    pub ok: bool,
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// Outcome of `validate_base_url`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BaseUrlValidation {
+    /// Root the monitor endpoints were resolved against
+    pub monitor_root: String,
+    pub endpoints: Vec<EndpointProbe>,
+}
+
+/// Kind of a quota, from the API's `type` identifier
+///
+/// Serialized as the raw identifier, e.g. "TOKENS_LIMIT".
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum QuotaKind {
+    /// Token quota over a rolling window ("TOKENS_LIMIT")
+    Tokens,
+    /// MCP tool call quota ("TIME_LIMIT")
+    Time,
+    /// A type this version doesn't know, with the raw identifier
+    Unknown(String),
+}
+
+impl QuotaKind {
+    /// Identifier as used by the API
+    pub fn as_raw(&self) -> &str {
+        match self {
+            QuotaKind::Tokens => "TOKENS_LIMIT",
+            QuotaKind::Time => "TIME_LIMIT",
+            QuotaKind::Unknown(raw) => raw,
+        }
+    }
+
+    /// Short name for config files, e.g. "tokens"
+    pub fn id(&self) -> &str {
+        match self {
+            QuotaKind::Tokens => "tokens",
+            QuotaKind::Time => "time",
+            QuotaKind::Unknown(raw) => raw,
+        }
+    }
+
+    /// Whether a quota filter such as an alert rule's `quota_type` selects this kind
+    ///
+    /// Accepts the short name or raw identifier, case-insensitively; "Token" and
+    /// "MCP" are kept for configs written before quota kinds existed.
+    /// An empty filter matches every kind.
+    pub fn matches(&self, filter: &str) -> bool {
+        let filter = filter.trim();
+        let alias = match filter.to_ascii_lowercase().as_str() {
+            "token" => Some(QuotaKind::Tokens),
+            "mcp" => Some(QuotaKind::Time),
+            _ => None,
+        };
+
+        filter.is_empty()
+            || filter.eq_ignore_ascii_case(self.id())
+            || filter.eq_ignore_ascii_case(self.as_raw())
+            || alias.as_ref() == Some(self)
+    }
+}
+
+impl From<String> for QuotaKind {
+    fn from(raw: String) -> Self {
+        match raw.as_str() {
+            // Older snapshots in the usage history stored display labels
+            "TOKENS_LIMIT" | "Token usage(5 Hour)" => QuotaKind::Tokens,
+            "TIME_LIMIT" | "MCP usage(1 Month)" => QuotaKind::Time,
+            _ => QuotaKind::Unknown(raw),
+        }
+    }
+}
+
+impl From<QuotaKind> for String {
+    fn from(kind: QuotaKind) -> Self {
+        kind.as_raw().to_string()
+    }
 }
 
 /// Usage detail for quota limits
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UsageDetail {
-    #[serde(rename(serialize = "tool_name", deserialize = "modelCode"))]
+    #[serde(rename(serialize = "tool_name", deserialize = "modelCode"), alias = "tool_name")]
     pub tool_name: String,
     pub usage: i64,
 }
@@ -136,9 +332,77 @@ pub struct UsageDetail {
 pub struct AllUsageData {
     pub model_usage: Vec<ModelUsageItem>,
     pub model_usage_timeseries: Option<ModelUsageTimeSeries>,
+    /// Time series per model code, keyed like `ModelUsageItem::model`
+    #[serde(default)]
+    pub model_usage_timeseries_by_model: BTreeMap<String, ModelUsageTimeSeries>,
     pub tool_usage: Vec<ToolUsageItem>,
     pub quota_limits: Vec<QuotaLimit>,
     pub timestamp: i64,
+    /// Window the model and tool usage were queried for
+    #[serde(default)]
+    pub time_window: TimeWindow,
+    /// Per-section fetch status; failed sections carry the previous data
+    #[serde(default)]
+    pub status: UsageStatus,
+    /// Name of the profile this snapshot belongs to
+    #[serde(default = "default_profile_name")]
+    pub profile: String,
+}
+
+impl AllUsageData {
+    /// The first quota of the given kind
+    pub fn quota(&self, kind: &QuotaKind) -> Option<&QuotaLimit> {
+        self.quota_limits.iter().find(|l| l.kind == *kind)
+    }
+}
+
+/// Fetch status of each independently refreshed section of `AllUsageData`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageStatus {
+    pub quota: SectionStatus,
+    pub models: SectionStatus,
+    pub tools: SectionStatus,
+}
+
+/// Status of one section (quota, models or tools)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SectionStatus {
+    /// When the data in this section was last fetched successfully
+    pub fetched_at: Option<i64>,
+    /// Error from the latest attempt; set means the data is stale
+    pub error: Option<SectionError>,
+}
+
+/// Serializable summary of the error that made a section stale
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SectionError {
+    pub kind: String,
+    pub message: String,
+}
+
+/// Time range for model and tool usage queries
+///
+/// Serialized with a `kind` tag, e.g. `{"kind": "last_7_days"}` or
+/// `{"kind": "custom", "start": 1700000000, "end": 1700086400}`.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TimeWindow {
+    /// Yesterday at the current hour through the end of the current hour
+    #[default]
+    #[serde(rename = "last_24_hours")]
+    Last24Hours,
+    /// The last 5 hours, matching the token quota window
+    #[serde(rename = "last_5_hours")]
+    Last5Hours,
+    /// Since local midnight
+    Today,
+    #[serde(rename = "last_7_days")]
+    Last7Days,
+    /// The last 30 days, roughly matching the monthly MCP quota
+    #[serde(rename = "last_30_days")]
+    Last30Days,
+    /// Explicit range in Unix seconds
+    Custom { start: i64, end: i64 },
 }
 
 /// Time-series data for model usage (charts)
@@ -153,26 +417,326 @@ pub struct ModelUsageTimeSeries {
 }
 
 /// Combined model usage result (aggregated items + time-series data)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ModelUsageResult {
     pub items: Vec<ModelUsageItem>,
     pub timeseries: Option<ModelUsageTimeSeries>,
+    /// Time series per model code, for per-model charts
+    pub timeseries_by_model: BTreeMap<String, ModelUsageTimeSeries>,
+}
+
+/// Predicted exhaustion of the 5-hour token quota at the current burn rate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuotaForecast {
+    pub profile: String,
+    /// Current token quota usage in percent
+    pub percentage: f64,
+    /// Percentage points consumed per hour at the current pace
+    pub burn_rate_per_hour: f64,
+    /// What the burn rate was derived from
+    pub source: ForecastSource,
+    /// Unix seconds at which the quota reaches 100%, if usage is growing
+    pub exhausted_at: Option<i64>,
+    /// Unix seconds of the next quota reset, if the API reported one
+    pub resets_at: Option<i64>,
+    /// Whether the quota runs out before it resets
+    pub exhausted_before_reset: bool,
+    pub computed_at: i64,
+}
+
+/// Data a burn rate was computed from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ForecastSource {
+    /// Quota percentages of recent history snapshots
+    History,
+    /// Recent hourly buckets of the `tokens_usage` time series
+    Timeseries,
 }
 
 /// Configuration structure
+///
+/// On disk `auth_token` is left empty and `auth_token_ref` points into the
+/// secret store; `config::load_config` fills the token back in.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub auth_token: String,
+    /// Secret store reference for `auth_token`, e.g. `keyring:profile:default`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_token_ref: Option<String>,
     pub base_url: String,
     pub refresh_interval_minutes: u64,
+    /// Days of usage history to keep on disk (0 = keep forever)
+    #[serde(default = "default_history_retention_days")]
+    pub history_retention_days: u64,
+    /// Retry behaviour for each monitor API request
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    /// Additional named accounts; `auth_token`/`base_url` above form the "default" profile
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
+    /// Profile shown in the tray and the webview
+    #[serde(default = "default_profile_name")]
+    pub active_profile: String,
+    /// Show the highest quota percentages across all profiles in the tray title
+    #[serde(default)]
+    pub tray_show_worst_case: bool,
+    /// Notify when the token quota is available again after running out
+    #[serde(default = "default_true")]
+    pub notify_quota_available: bool,
+    /// Where auth tokens are stored
+    #[serde(default)]
+    pub secret_backend: SecretBackend,
+    /// Root of the monitor API, e.g. `https://proxy.example.com/zai`; unset derives it from `base_url`
+    #[serde(default)]
+    pub monitor_base_url: Option<String>,
+    /// Address for the Prometheus metrics endpoint, e.g. `127.0.0.1:9464`; unset disables it
+    #[serde(default)]
+    pub metrics_listen_address: Option<String>,
+    /// Rules deciding when `usage-alert` events are emitted
+    #[serde(default = "default_alert_rules")]
+    pub alert_rules: Vec<AlertRule>,
+    /// Endpoints that receive a POST for every alert
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+    /// Tray title template, see `tray_title::TrayTemplate` for the syntax
+    #[serde(default = "default_tray_title_template")]
+    pub tray_title_template: String,
+    /// Most verbose log level written: error, warn, info, debug or trace
+    #[serde(default = "default_log_level")]
+    pub log_level: String,
+}
+
+/// Name of the implicit profile built from the top-level config fields
+pub const DEFAULT_PROFILE: &str = "default";
+
+fn default_profile_name() -> String {
+    DEFAULT_PROFILE.to_string()
+}
+
+/// A named API account
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub auth_token: String,
+    /// Secret store reference for `auth_token`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_token_ref: Option<String>,
+    pub base_url: String,
+    /// Overrides the monitor API root derived from `base_url`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monitor_base_url: Option<String>,
+}
+
+fn default_tray_title_template() -> String {
+    crate::tray_title::DEFAULT_TEMPLATE.to_string()
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+fn default_history_retention_days() -> u64 {
+    30
+}
+
+/// A user-defined condition on a quota that emits a `usage-alert` event
+///
+/// `percentage` and `burn_rate` rules fire when the value rises to `threshold`,
+/// `remaining` rules when it falls to it. A rule fires once, then stays silent
+/// until the value crosses back past `rearm_at` (defaults to `threshold`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertRule {
+    /// Unique name, used to track the rule's state
+    pub id: String,
+    /// Quota kind the rule applies to, e.g. "tokens" or "time"; empty matches every quota
+    ///
+    /// See `QuotaKind::matches` for the accepted names.
+    #[serde(default)]
+    pub quota_type: String,
+    #[serde(default)]
+    pub metric: AlertMetric,
+    pub threshold: f64,
+    pub severity: AlertSeverity,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rearm_at: Option<f64>,
+    /// Minimum time between two alerts from this rule
+    #[serde(default)]
+    pub cooldown_minutes: u64,
+    /// Local time range during which the rule stays silent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quiet_hours: Option<QuietHours>,
+}
+
+/// Value an alert rule compares against its threshold
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertMetric {
+    /// Quota used, in percent
+    #[default]
+    Percentage,
+    /// Absolute `remaining` count reported by the API
+    Remaining,
+    /// Percentage points per hour from the token quota forecast
+    BurnRate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertSeverity {
+    Info,
+    Warning,
+    Critical,
+}
+
+/// Local time range as "HH:MM"; `end` before `start` wraps past midnight
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start: String,
+    pub end: String,
+}
+
+/// Payload of the `usage-alert` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageAlert {
+    pub profile: String,
+    pub rule_id: String,
+    #[serde(rename = "type")]
+    pub quota: QuotaKind,
+    /// Display label of the quota, e.g. "Token usage (5 Hour)"
+    pub label: String,
+    pub metric: AlertMetric,
+    /// Value of `metric` that triggered the rule
+    pub value: f64,
+    pub threshold: f64,
+    /// Quota percentage at the time of the alert
+    pub percentage: f64,
+    pub severity: AlertSeverity,
+    pub timestamp: i64,
+}
+
+/// An outgoing webhook for alerts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    /// Unique name, shown in the delivery log
+    pub id: String,
+    pub url: String,
+    #[serde(default)]
+    pub preset: WebhookPreset,
+    /// Custom JSON body replacing the preset; strings may contain `{{placeholders}}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<serde_json::Value>,
+    /// Key for the `X-Zai-Signature` HMAC-SHA256 header; unsigned when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// Lowest severity delivered to this webhook
+    #[serde(default = "default_webhook_severity")]
+    pub min_severity: AlertSeverity,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+/// Body shape of a webhook
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookPreset {
+    /// The full alert as JSON
+    #[default]
+    Generic,
+    Slack,
+    Discord,
+    Teams,
+}
+
+/// Outcome of delivering one alert to one webhook
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub webhook_id: String,
+    pub rule_id: String,
+    pub profile: String,
+    pub timestamp: i64,
+    pub attempts: u32,
+    /// Status of the last response, if any arrived
+    pub status: Option<u16>,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+fn default_webhook_severity() -> AlertSeverity {
+    AlertSeverity::Info
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// The previous built-in behaviour: warn at 70% and 90% of every quota
+fn default_alert_rules() -> Vec<AlertRule> {
+    [("warning", 70.0, AlertSeverity::Warning), ("critical", 90.0, AlertSeverity::Critical)]
+        .into_iter()
+        .map(|(id, threshold, severity)| AlertRule {
+            id: id.to_string(),
+            quota_type: String::new(),
+            metric: AlertMetric::Percentage,
+            threshold,
+            severity,
+            rearm_at: None,
+            cooldown_minutes: 0,
+            quiet_hours: None,
+        })
+        .collect()
+}
+
+/// Retry policy for Z.ai monitor requests
+///
+/// Network errors, 429 and 5xx responses are retried with exponential
+/// backoff. `jitter` is the fraction (0.0-1.0) by which each delay is
+/// randomly spread to avoid synchronized retries.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Total attempts including the first one (1 disables retries)
+    pub max_attempts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub multiplier: f64,
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 30_000,
+            multiplier: 2.0,
+            jitter: 0.2,
+        }
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             auth_token: String::new(),
+            auth_token_ref: None,
             base_url: "https://api.z.ai/api/anthropic".to_string(),
             refresh_interval_minutes: 5,
+            history_retention_days: default_history_retention_days(),
+            retry: RetryPolicy::default(),
+            profiles: Vec::new(),
+            active_profile: default_profile_name(),
+            tray_show_worst_case: false,
+            notify_quota_available: true,
+            secret_backend: SecretBackend::default(),
+            monitor_base_url: None,
+            metrics_listen_address: None,
+            alert_rules: default_alert_rules(),
+            webhooks: Vec::new(),
+            tray_title_template: default_tray_title_template(),
+            log_level: default_log_level(),
         }
     }
 }
@@ -0,0 +1,358 @@
+use crate::error::AppError;
+use crate::retry::with_retry;
+use crate::types::{
+    AlertMetric, AlertSeverity, RetryPolicy, UsageAlert, WebhookConfig, WebhookDelivery,
+    WebhookPreset,
+};
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Delivery log entries kept on disk
+const MAX_LOG_ENTRIES: usize = 500;
+
+/// Per-request timeout, so a hanging endpoint can't stall alert delivery
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Sends alerts to configured webhooks and records every delivery
+///
+/// Bodies are signed with HMAC-SHA256 over `"{timestamp}.{body}"` when the
+/// webhook has a secret; the signature goes in `X-Zai-Signature` as
+/// `sha256=<hex>` and the timestamp in `X-Zai-Timestamp`.
+pub struct WebhookDispatcher {
+    client: Client,
+    log_path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl WebhookDispatcher {
+    /// Create a dispatcher that logs deliveries to the given JSON lines file
+    pub fn new(log_path: PathBuf) -> Self {
+        Self {
+            client: Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            log_path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Create a dispatcher logging to the default app data directory
+    pub fn open_default() -> Self {
+        Self::new(crate::config::get_data_dir().join("webhook_deliveries.jsonl"))
+    }
+
+    /// Deliver `alert` to every enabled webhook whose `min_severity` it meets
+    pub async fn dispatch(
+        &self,
+        webhooks: &[WebhookConfig],
+        alert: &UsageAlert,
+        policy: &RetryPolicy,
+    ) -> Vec<WebhookDelivery> {
+        let targets = webhooks
+            .iter()
+            .filter(|hook| hook.enabled && alert.severity >= hook.min_severity);
+
+        futures::future::join_all(targets.map(|hook| self.deliver(hook, alert, policy))).await
+    }
+
+    /// Deliver `alert` to a single webhook, retrying per `policy`, and log the outcome
+    pub async fn deliver(
+        &self,
+        hook: &WebhookConfig,
+        alert: &UsageAlert,
+        policy: &RetryPolicy,
+    ) -> WebhookDelivery {
+        let body = render_body(hook, alert).to_string();
+        let attempts = AtomicU32::new(0);
+        let result = with_retry(policy, || {
+            attempts.fetch_add(1, Ordering::Relaxed);
+            self.send_once(hook, &body)
+        })
+        .await;
+
+        let status = match &result {
+            Ok(status) => Some(*status),
+            Err(e) => status_of(e),
+        };
+        let delivery = WebhookDelivery {
+            webhook_id: hook.id.clone(),
+            rule_id: alert.rule_id.clone(),
+            profile: alert.profile.clone(),
+            timestamp: chrono::Local::now().timestamp(),
+            attempts: attempts.into_inner(),
+            status,
+            success: result.is_ok(),
+            // The log is shown in the UI, so keep credentials out of it
+            error: result.err().map(|e| crate::logging::redact(&e.to_string())),
+        };
+
+        if let Err(e) = self.record(&delivery) {
+            tracing::warn!(error = %e, "Failed to record webhook delivery");
+        }
+        delivery
+    }
+
+    /// POST the body once and map non-success statuses to errors
+    async fn send_once(&self, hook: &WebhookConfig, body: &str) -> Result<u16, AppError> {
+        let timestamp = chrono::Local::now().timestamp().to_string();
+        let mut request = self
+            .client
+            .post(&hook.url)
+            .header("Content-Type", "application/json")
+            .header("X-Zai-Event", "usage-alert")
+            .header("X-Zai-Timestamp", &timestamp)
+            .body(body.to_string());
+
+        if let Some(secret) = hook.secret.as_deref().filter(|s| !s.is_empty()) {
+            request = request.header("X-Zai-Signature", sign(secret, &timestamp, body));
+        }
+
+        // Webhook URLs often carry their credentials, so errors must not include them
+        let response = request.send().await.map_err(|e| AppError::from(e.without_url()))?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(status.as_u16());
+        }
+
+        let retry_after_secs = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(crate::api::parse_retry_after);
+        let body = crate::api::excerpt(&response.text().await.unwrap_or_default());
+
+        Err(match status.as_u16() {
+            429 => AppError::RateLimited { retry_after_secs },
+            status => AppError::Http {
+                status,
+                body,
+                retry_after_secs,
+            },
+        })
+    }
+
+    /// Most recent deliveries, newest first
+    pub fn deliveries(&self, limit: usize) -> Result<Vec<WebhookDelivery>, AppError> {
+        let _guard = self.lock.lock().unwrap();
+        let mut deliveries = self.read_log()?;
+        deliveries.reverse();
+        deliveries.truncate(limit);
+        Ok(deliveries)
+    }
+
+    /// Append a delivery to the log, dropping the oldest entries beyond `MAX_LOG_ENTRIES`
+    fn record(&self, delivery: &WebhookDelivery) -> Result<(), AppError> {
+        let _guard = self.lock.lock().unwrap();
+
+        let mut deliveries = self.read_log()?;
+        deliveries.push(delivery.clone());
+        let skip = deliveries.len().saturating_sub(MAX_LOG_ENTRIES);
+
+        let mut contents = String::new();
+        for delivery in &deliveries[skip..] {
+            let line = serde_json::to_string(delivery)
+                .map_err(|e| AppError::Storage(format!("Failed to serialize delivery: {}", e)))?;
+            contents.push_str(&line);
+            contents.push('\n');
+        }
+
+        if let Some(parent) = self.log_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| AppError::Storage(format!("Failed to create delivery log directory: {}", e)))?;
+        }
+        fs::write(&self.log_path, contents)
+            .map_err(|e| AppError::Storage(format!("Failed to write delivery log: {}", e)))
+    }
+
+    /// Read every logged delivery, skipping lines that fail to parse
+    fn read_log(&self) -> Result<Vec<WebhookDelivery>, AppError> {
+        if !self.log_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let contents = fs::read_to_string(&self.log_path)
+            .map_err(|e| AppError::Storage(format!("Failed to read delivery log: {}", e)))?;
+        Ok(contents
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
+
+/// HTTP status carried by a delivery error, if a response arrived
+fn status_of(error: &AppError) -> Option<u16> {
+    match error {
+        AppError::Http { status, .. } | AppError::Unauthorized { status } => Some(*status),
+        AppError::RateLimited { .. } => Some(429),
+        AppError::RetriesExhausted { last, .. } => status_of(last),
+        _ => None,
+    }
+}
+
+/// `sha256=<hex>` HMAC of `"{timestamp}.{body}"` under `secret`
+pub fn sign(secret: &str, timestamp: &str, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+
+    let hex: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("sha256={}", hex)
+}
+
+/// Build the JSON body for an alert from the webhook's template or preset
+///
+/// String values may contain placeholders: `{{profile}}`, `{{rule_id}}`, `{{type}}`,
+/// `{{metric}}`, `{{value}}`, `{{threshold}}`, `{{percentage}}`, `{{severity}}`,
+/// `{{timestamp}}`, `{{message}}` and `{{color}}`. A string consisting of exactly
+/// one numeric placeholder is replaced by the number itself.
+pub fn render_body(hook: &WebhookConfig, alert: &UsageAlert) -> Value {
+    let template = match &hook.template {
+        Some(template) => template.clone(),
+        None => preset_template(hook.preset),
+    };
+    substitute(template, &placeholders(alert))
+}
+
+/// Default body template for each preset
+fn preset_template(preset: WebhookPreset) -> Value {
+    match preset {
+        WebhookPreset::Generic => json!({
+            "event": "usage-alert",
+            "message": "{{message}}",
+            "alert": {
+                "profile": "{{profile}}",
+                "rule_id": "{{rule_id}}",
+                "type": "{{type}}",
+                "metric": "{{metric}}",
+                "value": "{{value}}",
+                "threshold": "{{threshold}}",
+                "percentage": "{{percentage}}",
+                "severity": "{{severity}}",
+                "timestamp": "{{timestamp}}"
+            }
+        }),
+        WebhookPreset::Slack => json!({ "text": "{{message}}" }),
+        WebhookPreset::Discord => json!({ "content": "{{message}}" }),
+        WebhookPreset::Teams => json!({
+            "@type": "MessageCard",
+            "@context": "https://schema.org/extensions",
+            "summary": "{{message}}",
+            "themeColor": "{{color}}",
+            "title": "Z.ai usage alert",
+            "text": "{{message}}"
+        }),
+    }
+}
+
+/// Placeholder names and values for an alert
+fn placeholders(alert: &UsageAlert) -> Vec<(&'static str, Value)> {
+    let (metric, detail) = match alert.metric {
+        AlertMetric::Percentage => ("percentage", format!("{:.1}% used", alert.value)),
+        AlertMetric::Remaining => ("remaining", format!("{} remaining", alert.value)),
+        AlertMetric::BurnRate => ("burn_rate", format!("burning {:.1}%/h", alert.value)),
+    };
+    let (severity, color) = match alert.severity {
+        AlertSeverity::Info => ("info", "2E86DE"),
+        AlertSeverity::Warning => ("warning", "F39C12"),
+        AlertSeverity::Critical => ("critical", "E74C3C"),
+    };
+    let message = format!(
+        "[{}] {} ({}): {}",
+        severity.to_uppercase(),
+        alert.label,
+        alert.profile,
+        detail
+    );
+
+    vec![
+        ("profile", json!(alert.profile)),
+        ("rule_id", json!(alert.rule_id)),
+        ("type", json!(alert.quota)),
+        ("label", json!(alert.label)),
+        ("metric", json!(metric)),
+        ("value", json!(alert.value)),
+        ("threshold", json!(alert.threshold)),
+        ("percentage", json!(alert.percentage)),
+        ("severity", json!(severity)),
+        ("timestamp", json!(alert.timestamp)),
+        ("message", json!(message)),
+        ("color", json!(color)),
+    ]
+}
+
+/// Replace placeholders in every string of a JSON template
+fn substitute(template: Value, placeholders: &[(&'static str, Value)]) -> Value {
+    match template {
+        Value::String(text) => {
+            // Keep numbers typed when the whole string is a single placeholder
+            if let Some((_, value)) = placeholders
+                .iter()
+                .find(|(name, _)| text == format!("{{{{{}}}}}", name))
+            {
+                return value.clone();
+            }
+
+            let mut text = text;
+            for (name, value) in placeholders {
+                let replacement = match value {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                text = text.replace(&format!("{{{{{}}}}}", name), &replacement);
+            }
+            Value::String(text)
+        }
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(|item| substitute(item, placeholders))
+                .collect(),
+        ),
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| (key, substitute(value, placeholders)))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+/// Check webhooks for mistakes before they are saved
+pub fn validate_webhooks(webhooks: &[WebhookConfig]) -> Result<(), AppError> {
+    let mut ids = std::collections::BTreeSet::new();
+
+    for hook in webhooks {
+        if hook.id.trim().is_empty() {
+            return Err(AppError::InvalidInput("Webhook id cannot be empty".to_string()));
+        }
+        if !ids.insert(hook.id.as_str()) {
+            return Err(AppError::InvalidInput(format!("Duplicate webhook id: {}", hook.id)));
+        }
+        match reqwest::Url::parse(&hook.url) {
+            Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {}
+            _ => {
+                return Err(AppError::InvalidInput(format!(
+                    "Webhook {} needs an http(s) URL",
+                    hook.id
+                )))
+            }
+        }
+    }
+
+    Ok(())
+}
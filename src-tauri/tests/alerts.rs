@@ -0,0 +1,169 @@
+mod common;
+
+use chrono::{Local, NaiveDate, TimeZone};
+use common::{temp_dir, token_quota, usage_data};
+use zai_usage_monitor_lib::alerts::{validate_rules, AlertEngine};
+use zai_usage_monitor_lib::types::{AlertMetric, AlertRule, AlertSeverity, QuietHours};
+
+const NOW: i64 = 1_767_200_000;
+
+fn rule(metric: AlertMetric, threshold: f64, rearm_at: Option<f64>) -> AlertRule {
+    AlertRule {
+        id: "rule".to_string(),
+        quota_type: "tokens".to_string(),
+        metric,
+        threshold,
+        severity: AlertSeverity::Warning,
+        rearm_at,
+        cooldown_minutes: 0,
+        quiet_hours: None,
+    }
+}
+
+fn engine(name: &str) -> AlertEngine {
+    AlertEngine::new(temp_dir(name).join("alert_state.json"))
+}
+
+/// Number of alerts `rules` raise for a token quota at `percentage` used
+fn fired(engine: &AlertEngine, rules: &[AlertRule], percentage: f64, now: i64) -> usize {
+    engine
+        .evaluate(rules, &usage_data(vec![token_quota(percentage, None)], now), None, now)
+        .unwrap()
+        .len()
+}
+
+/// Unix time of a local wall-clock time
+fn local(date: NaiveDate, hour: u32, minute: u32) -> i64 {
+    Local
+        .from_local_datetime(&date.and_hms_opt(hour, minute, 0).unwrap())
+        .earliest()
+        .unwrap()
+        .timestamp()
+}
+
+#[test]
+fn rule_fires_once_and_rearms_below_the_rearm_level() {
+    let engine = engine("alerts-rearm");
+    let rules = [rule(AlertMetric::Percentage, 80.0, Some(70.0))];
+
+    assert_eq!(fired(&engine, &rules, 79.9, NOW), 0);
+    assert_eq!(fired(&engine, &rules, 85.0, NOW + 60), 1);
+    assert_eq!(fired(&engine, &rules, 90.0, NOW + 120), 0);
+    // Between the re-arm level and the threshold the rule stays disarmed
+    assert_eq!(fired(&engine, &rules, 75.0, NOW + 180), 0);
+    assert_eq!(fired(&engine, &rules, 85.0, NOW + 240), 0);
+    assert_eq!(fired(&engine, &rules, 65.0, NOW + 300), 0);
+    assert_eq!(fired(&engine, &rules, 85.0, NOW + 360), 1);
+}
+
+#[test]
+fn rearm_level_defaults_to_the_threshold() {
+    let engine = engine("alerts-rearm-default");
+    let rules = [rule(AlertMetric::Percentage, 80.0, None)];
+
+    assert_eq!(fired(&engine, &rules, 80.0, NOW), 1);
+    assert_eq!(fired(&engine, &rules, 79.0, NOW + 60), 0);
+    assert_eq!(fired(&engine, &rules, 80.0, NOW + 120), 1);
+}
+
+#[test]
+fn cooldown_delays_an_alert_without_dropping_it() {
+    let engine = engine("alerts-cooldown");
+    let mut cooling = rule(AlertMetric::Percentage, 80.0, None);
+    cooling.cooldown_minutes = 60;
+    let rules = [cooling];
+
+    assert_eq!(fired(&engine, &rules, 85.0, NOW), 1);
+    assert_eq!(fired(&engine, &rules, 50.0, NOW + 60), 0);
+    assert_eq!(fired(&engine, &rules, 85.0, NOW + 600), 0);
+    assert_eq!(fired(&engine, &rules, 85.0, NOW + 3599), 0);
+    assert_eq!(fired(&engine, &rules, 85.0, NOW + 3600), 1);
+}
+
+#[test]
+fn quiet_hours_wrap_past_midnight() {
+    let engine = engine("alerts-quiet");
+    let mut quiet = rule(AlertMetric::Percentage, 80.0, None);
+    quiet.quiet_hours = Some(QuietHours {
+        start: "22:00".to_string(),
+        end: "06:00".to_string(),
+    });
+    let rules = [quiet];
+    let day = NaiveDate::from_ymd_opt(2026, 1, 15).unwrap();
+    let next_day = day.succ_opt().unwrap();
+
+    assert_eq!(fired(&engine, &rules, 85.0, local(day, 22, 0)), 0);
+    assert_eq!(fired(&engine, &rules, 85.0, local(day, 23, 30)), 0);
+    assert_eq!(fired(&engine, &rules, 85.0, local(next_day, 0, 30)), 0);
+    assert_eq!(fired(&engine, &rules, 85.0, local(next_day, 5, 59)), 0);
+    // The rule stayed armed and fires as soon as the quiet period ends
+    assert_eq!(fired(&engine, &rules, 85.0, local(next_day, 6, 0)), 1);
+}
+
+#[test]
+fn quiet_hours_within_a_day() {
+    let engine = engine("alerts-quiet-day");
+    let mut quiet = rule(AlertMetric::Percentage, 80.0, None);
+    quiet.quiet_hours = Some(QuietHours {
+        start: "12:00".to_string(),
+        end: "13:00".to_string(),
+    });
+    let rules = [quiet];
+    let day = NaiveDate::from_ymd_opt(2026, 1, 15).unwrap();
+
+    assert_eq!(fired(&engine, &rules, 85.0, local(day, 12, 30)), 0);
+    assert_eq!(fired(&engine, &rules, 85.0, local(day, 23, 30)), 1);
+}
+
+#[test]
+fn remaining_alerts_when_falling_below_the_threshold() {
+    let engine = engine("alerts-remaining");
+    let rules = [rule(AlertMetric::Remaining, 100.0, Some(200.0))];
+    let fired = |remaining: i64, now: i64| {
+        engine
+            .evaluate(&rules, &usage_data(vec![token_quota(50.0, Some(remaining))], now), None, now)
+            .unwrap()
+    };
+
+    assert!(fired(150, NOW).is_empty());
+    let alerts = fired(90, NOW + 60);
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].value, 90.0);
+    assert_eq!(alerts[0].metric, AlertMetric::Remaining);
+    // Rising back above the threshold, but not the re-arm level, keeps it disarmed
+    assert!(fired(150, NOW + 120).is_empty());
+    assert!(fired(50, NOW + 180).is_empty());
+    assert!(fired(250, NOW + 240).is_empty());
+    assert_eq!(fired(50, NOW + 300).len(), 1);
+}
+
+#[test]
+fn state_survives_a_restart() {
+    let path = temp_dir("alerts-restart").join("alert_state.json");
+    let rules = [rule(AlertMetric::Percentage, 80.0, None)];
+
+    assert_eq!(fired(&AlertEngine::new(path.clone()), &rules, 85.0, NOW), 1);
+    assert!(path.exists());
+
+    // A new engine on the same file remembers that the rule already fired
+    let restarted = AlertEngine::new(path.clone());
+    assert_eq!(fired(&restarted, &rules, 90.0, NOW + 60), 0);
+
+    // Editing the rules away forgets their state
+    restarted.retain_rules(&[]).unwrap();
+    let after_edit = AlertEngine::new(path);
+    assert_eq!(fired(&after_edit, &rules, 90.0, NOW + 120), 1);
+}
+
+#[test]
+fn rearm_level_past_the_threshold_is_rejected() {
+    let message = |rule: AlertRule| validate_rules(&[rule]).unwrap_err().to_string();
+
+    assert!(message(rule(AlertMetric::Percentage, 80.0, Some(85.0))).contains("must re-arm below"));
+    assert!(message(rule(AlertMetric::BurnRate, 20.0, Some(25.0))).contains("must re-arm below"));
+    assert!(message(rule(AlertMetric::Remaining, 100.0, Some(50.0))).contains("must re-arm above"));
+
+    assert!(validate_rules(&[rule(AlertMetric::Percentage, 80.0, Some(70.0))]).is_ok());
+    assert!(validate_rules(&[rule(AlertMetric::Percentage, 80.0, Some(80.0))]).is_ok());
+    assert!(validate_rules(&[rule(AlertMetric::Remaining, 100.0, Some(200.0))]).is_ok());
+}
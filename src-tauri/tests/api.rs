@@ -0,0 +1,414 @@
+mod common;
+
+use common::zai::{fixture, MockZai, MODEL_USAGE_PATH, QUOTA_PATH, TOKEN, TOOL_USAGE_PATH};
+use common::{fast_retries, token_quota, usage_data, Reply};
+use std::time::Duration;
+use zai_usage_monitor_lib::api::{FetchResults, UsageClient, MAX_MODEL_BREAKDOWN};
+use zai_usage_monitor_lib::error::AppError;
+use zai_usage_monitor_lib::types::{
+    Config, ModelUsageItem, ModelUsageResult, QuotaKind, QuotaLimit, QuotaWindow, TimeWindow, ToolUsageItem, WindowUnit,
+};
+
+#[tokio::test]
+async fn fetch_all_parses_every_endpoint() {
+    let mock = MockZai::start(vec![]).await;
+
+    let FetchResults { quota, models, tools } = mock
+        .client(fast_retries(1))
+        .fetch_all(&TimeWindow::default())
+        .await;
+
+    let quota = quota.unwrap();
+    assert_eq!(quota.len(), 2);
+    assert_eq!(quota[0].kind, QuotaKind::Tokens);
+    assert_eq!(quota[0].percentage, 42.5);
+    assert_eq!(quota[0].window, Some(QuotaWindow { unit: WindowUnit::Hour, number: 5 }));
+    assert_eq!(quota[0].resets_at, Some(1_767_225_600));
+    assert_eq!(quota[0].window_start, Some(1_767_225_600 - 5 * 3600));
+    assert_eq!(quota[1].kind, QuotaKind::Time);
+    assert_eq!(quota[1].window, Some(QuotaWindow { unit: WindowUnit::Month, number: 1 }));
+    assert_eq!(quota[1].remaining, Some(880));
+
+    // One item per model listed in the token quota, with its own figures
+    let models = models.unwrap();
+    let names: Vec<&str> = models.items.iter().map(|item| item.model.as_str()).collect();
+    assert_eq!(names, vec!["glm-4.5-air", "glm-4.6"]);
+    assert_eq!(models.items[0].token_count, 150_000);
+    assert_eq!(models.items[0].request_count, 12);
+    assert_eq!(models.items[1].token_count, 420_000);
+    assert_eq!(models.items[1].request_count, 30);
+    assert_eq!(models.timeseries.unwrap().tokens_usage, vec![Some(150_000), None, Some(420_000)]);
+    assert_eq!(models.timeseries_by_model.len(), 2);
+
+    let tools = tools.unwrap();
+    assert_eq!(tools.len(), 2);
+    assert_eq!(tools[0].tool_name, "search-prime");
+    assert_eq!(tools[0].usage_count, 5);
+}
+
+#[tokio::test]
+async fn requests_carry_the_token_and_time_window() {
+    let mock = MockZai::start(vec![]).await;
+    mock.client(fast_retries(1)).fetch_tool_usage(&TimeWindow::default()).await.unwrap();
+
+    let requests = mock.requests_to(TOOL_USAGE_PATH);
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, "GET");
+    assert_eq!(requests[0].header("authorization"), Some(TOKEN));
+    assert!(requests[0].path.contains("startTime="));
+    assert!(requests[0].path.contains("endTime="));
+}
+
+#[tokio::test]
+async fn today_starts_at_local_midnight() {
+    let mock = MockZai::start(vec![]).await;
+    mock.client(fast_retries(1)).fetch_tool_usage(&TimeWindow::Today).await.unwrap();
+
+    let path = &mock.requests_to(TOOL_USAGE_PATH)[0].path;
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    assert!(path.contains(&format!("startTime={}+00%3A00%3A00", today)), "{}", path);
+}
+
+#[tokio::test]
+async fn model_usage_is_requested_per_model_code() {
+    let mock = MockZai::start(vec![]).await;
+    let codes = vec!["glm-4.6".to_string()];
+
+    mock.client(fast_retries(1))
+        .fetch_model_usage(&TimeWindow::default(), &codes)
+        .await
+        .unwrap();
+
+    let requests = mock.requests_to(MODEL_USAGE_PATH);
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests.iter().filter(|r| r.path.contains("modelCode=glm-4.6")).count(), 1);
+}
+
+#[tokio::test]
+async fn ignored_model_filter_falls_back_to_account_totals() {
+    // Every request gets the account-wide figures, whatever its modelCode
+    let mock = MockZai::start(vec![(MODEL_USAGE_PATH, vec![Reply::new(200, &fixture("model_usage.json"))])]).await;
+    let codes = vec!["glm-4.5-air".to_string(), "glm-4.6".to_string()];
+
+    let models = mock
+        .client(fast_retries(1))
+        .fetch_model_usage(&TimeWindow::default(), &codes)
+        .await
+        .unwrap();
+
+    assert_eq!(models.items.len(), 1);
+    assert_eq!(models.items[0].model, "All Models");
+    assert_eq!(models.items[0].token_count, 570_000);
+    assert!(models.timeseries_by_model.is_empty());
+}
+
+#[tokio::test]
+async fn ignored_model_filter_is_detected_for_a_single_model() {
+    let mock = MockZai::start(vec![(MODEL_USAGE_PATH, vec![Reply::new(200, &fixture("model_usage.json"))])]).await;
+    let codes = vec!["glm-4.6".to_string()];
+
+    let models = mock
+        .client(fast_retries(1))
+        .fetch_model_usage(&TimeWindow::default(), &codes)
+        .await
+        .unwrap();
+
+    assert_eq!(models.items.len(), 1);
+    assert_eq!(models.items[0].model, "All Models");
+    assert!(models.timeseries_by_model.is_empty());
+}
+
+#[tokio::test]
+async fn single_model_with_part_of_the_usage_is_kept() {
+    let mock = MockZai::start(vec![]).await;
+    let codes = vec!["glm-4.6".to_string()];
+
+    let models = mock
+        .client(fast_retries(1))
+        .fetch_model_usage(&TimeWindow::default(), &codes)
+        .await
+        .unwrap();
+
+    assert_eq!(models.items.len(), 1);
+    assert_eq!(models.items[0].model, "glm-4.6");
+    assert!(models.timeseries_by_model.contains_key("glm-4.6"));
+}
+
+#[test]
+fn model_codes_are_capped_to_the_heaviest_models() {
+    let details: Vec<serde_json::Value> = (0..8)
+        .map(|i| serde_json::json!({ "modelCode": format!("model-{}", i), "usage": i * 100 }))
+        .collect();
+    let limits: Vec<QuotaLimit> = serde_json::from_value(serde_json::json!([
+        { "type": "TOKENS_LIMIT", "unit": 3, "number": 5, "percentage": 10, "usageDetails": details }
+    ]))
+    .unwrap();
+
+    let codes = UsageClient::model_codes(&limits);
+
+    assert_eq!(codes.len(), MAX_MODEL_BREAKDOWN);
+    assert_eq!(codes, vec!["model-3", "model-4", "model-5", "model-6", "model-7"]);
+}
+
+#[tokio::test]
+async fn unauthorized_is_reported_without_retrying() {
+    let mock = MockZai::start(vec![(QUOTA_PATH, vec![Reply::new(401, "invalid token")])]).await;
+
+    let error = mock.client(fast_retries(3)).fetch_quota_limits().await.unwrap_err();
+
+    assert!(matches!(error, AppError::Unauthorized { status: 401 }));
+    assert!(error.is_auth_error());
+    assert_eq!(mock.requests_to(QUOTA_PATH).len(), 1);
+}
+
+#[tokio::test]
+async fn rate_limits_are_retried_after_the_requested_delay() {
+    let mock = MockZai::start(vec![(
+        QUOTA_PATH,
+        vec![
+            Reply::new(429, "slow down").header("Retry-After", "0"),
+            Reply::new(200, &fixture("quota_limit.json")),
+        ],
+    )])
+    .await;
+
+    let quota = mock.client(fast_retries(3)).fetch_quota_limits().await.unwrap();
+
+    assert_eq!(quota.len(), 2);
+    assert_eq!(mock.requests_to(QUOTA_PATH).len(), 2);
+}
+
+#[tokio::test]
+async fn persistent_rate_limits_exhaust_retries() {
+    let mock = MockZai::start(vec![(QUOTA_PATH, vec![Reply::new(429, "slow down")])]).await;
+
+    let error = mock.client(fast_retries(2)).fetch_quota_limits().await.unwrap_err();
+
+    assert_eq!(error.kind(), "rate_limited");
+    assert_eq!(error.attempts(), Some(2));
+}
+
+#[tokio::test]
+async fn server_errors_are_retried_until_attempts_run_out() {
+    let mock = MockZai::start(vec![(TOOL_USAGE_PATH, vec![Reply::new(500, "internal error")])]).await;
+
+    let error = mock
+        .client(fast_retries(3))
+        .fetch_tool_usage(&TimeWindow::default())
+        .await
+        .unwrap_err();
+
+    assert_eq!(error.kind(), "http");
+    assert_eq!(error.attempts(), Some(3));
+    assert!(error.to_string().contains("internal error"));
+    assert_eq!(mock.requests_to(TOOL_USAGE_PATH).len(), 3);
+}
+
+#[tokio::test]
+async fn malformed_json_is_a_parse_error() {
+    let mock = MockZai::start(vec![(QUOTA_PATH, vec![Reply::new(200, "{\"code\": 200, \"data\": ")])]).await;
+
+    let error = mock.client(fast_retries(3)).fetch_quota_limits().await.unwrap_err();
+
+    assert!(matches!(error, AppError::Parse(_)));
+    assert_eq!(mock.requests_to(QUOTA_PATH).len(), 1);
+}
+
+#[tokio::test]
+async fn business_failures_carry_the_server_message() {
+    let mock = MockZai::start(vec![(
+        QUOTA_PATH,
+        vec![Reply::new(200, r#"{"code": 1113, "msg": "Insufficient balance", "success": false}"#)],
+    )])
+    .await;
+
+    let error = mock.client(fast_retries(3)).fetch_quota_limits().await.unwrap_err();
+
+    match &error {
+        AppError::Api { code, msg } => {
+            assert_eq!(*code, Some(1113));
+            assert_eq!(msg, "Insufficient balance");
+        }
+        other => panic!("expected an API error, got {:?}", other),
+    }
+    assert_eq!(error.kind(), "api");
+    assert_eq!(error.to_string(), "API error 1113: Insufficient balance");
+    assert_eq!(mock.requests_to(QUOTA_PATH).len(), 1);
+}
+
+#[tokio::test]
+async fn failures_with_null_or_mismatched_data_are_api_errors() {
+    let mock = MockZai::start(vec![
+        (TOOL_USAGE_PATH, vec![Reply::new(200, r#"{"code": 500, "msg": "busy", "success": false, "data": null}"#)]),
+        (QUOTA_PATH, vec![Reply::new(200, r#"{"code": 200, "msg": "", "success": false, "data": "n/a"}"#)]),
+    ])
+    .await;
+    let client = mock.client(fast_retries(1));
+
+    let tools = client.fetch_tool_usage(&TimeWindow::default()).await.unwrap_err();
+    assert!(matches!(tools, AppError::Api { code: Some(500), .. }));
+
+    let quota = client.fetch_quota_limits().await.unwrap_err();
+    assert_eq!(quota.kind(), "api");
+    assert!(quota.to_string().contains("not successful"));
+}
+
+#[tokio::test]
+async fn success_without_data_is_a_parse_error() {
+    let mock = MockZai::start(vec![(
+        QUOTA_PATH,
+        vec![Reply::new(200, r#"{"code": 200, "msg": "ok", "success": true}"#)],
+    )])
+    .await;
+
+    let error = mock.client(fast_retries(1)).fetch_quota_limits().await.unwrap_err();
+
+    assert!(matches!(error, AppError::Parse(_)));
+}
+
+#[tokio::test]
+async fn slow_responses_time_out() {
+    let slow = Reply::new(200, &fixture("quota_limit.json")).delay(Duration::from_secs(5));
+    let mock = MockZai::start(vec![(QUOTA_PATH, vec![slow])]).await;
+
+    let error = mock
+        .client(fast_retries(1))
+        .with_timeout(Duration::from_millis(200))
+        .unwrap()
+        .fetch_quota_limits()
+        .await
+        .unwrap_err();
+
+    assert_eq!(error.kind(), "network");
+}
+
+#[tokio::test]
+async fn one_failing_section_leaves_the_others_intact() {
+    let mock = MockZai::start(vec![(TOOL_USAGE_PATH, vec![Reply::new(503, "maintenance")])]).await;
+
+    let results = mock
+        .client(fast_retries(1))
+        .fetch_all(&TimeWindow::default())
+        .await;
+
+    assert!(results.quota.is_ok());
+    assert!(results.models.is_ok());
+    assert_eq!(results.tools.unwrap_err().kind(), "http");
+}
+
+fn config(base_url: &str, monitor_base_url: Option<String>) -> Config {
+    Config {
+        auth_token: TOKEN.to_string(),
+        base_url: base_url.to_string(),
+        monitor_base_url,
+        retry: fast_retries(1),
+        ..Config::default()
+    }
+}
+
+#[tokio::test]
+async fn monitor_root_is_derived_from_any_base_url() {
+    let mock = MockZai::start(vec![]).await;
+    let base_url = format!("{}/api/anthropic", mock.server.url);
+
+    let quota = UsageClient::new(config(&base_url, None)).fetch_quota_limits().await.unwrap();
+
+    assert_eq!(quota.len(), 2);
+    assert_eq!(mock.requests_to(QUOTA_PATH).len(), 1);
+}
+
+#[tokio::test]
+async fn monitor_base_url_overrides_the_base_url() {
+    let mock = MockZai::start(vec![]).await;
+    let config = config("https://llm.example.com/v1", Some(format!("{}/", mock.server.url)));
+
+    UsageClient::new(config).fetch_quota_limits().await.unwrap();
+
+    assert_eq!(mock.requests_to(QUOTA_PATH).len(), 1);
+}
+
+#[test]
+fn base_urls_map_to_monitor_roots() {
+    let root = |url: &str| UsageClient::base_domain(url).unwrap();
+
+    assert_eq!(root("https://api.z.ai/api/anthropic"), "https://api.z.ai");
+    assert_eq!(root("https://dev.bigmodel.cn/api/paas/v4"), "https://open.bigmodel.cn");
+    assert_eq!(root("https://proxy.example.com/zai/api/anthropic"), "https://proxy.example.com/zai");
+    assert_eq!(root("http://localhost:8080"), "http://localhost:8080");
+    // Look-alike hosts are not treated as Z.ai
+    assert_eq!(root("https://api.z.ai.example.com/api/anthropic"), "https://api.z.ai.example.com");
+
+    assert!(matches!(UsageClient::base_domain("api.z.ai"), Err(AppError::InvalidBaseUrl(_))));
+    assert!(matches!(UsageClient::base_domain("ftp://example.com"), Err(AppError::InvalidBaseUrl(_))));
+}
+
+#[tokio::test]
+async fn probe_reports_which_endpoints_work() {
+    let mock = MockZai::start(vec![
+        (TOOL_USAGE_PATH, vec![Reply::new(404, "not found")]),
+        (MODEL_USAGE_PATH, vec![Reply::new(200, "<html>proxy login</html>")]),
+    ])
+    .await;
+
+    let report = mock.client(fast_retries(1)).probe_endpoints().await.unwrap();
+
+    assert_eq!(report.monitor_root, mock.server.url);
+    let endpoints: Vec<(&str, bool, Option<u16>)> = report
+        .endpoints
+        .iter()
+        .map(|probe| (probe.endpoint.as_str(), probe.ok, probe.status))
+        .collect();
+    assert_eq!(
+        endpoints,
+        vec![
+            ("quota", true, Some(200)),
+            ("model_usage", false, None),
+            ("tool_usage", false, Some(404)),
+        ]
+    );
+    assert!(report.endpoints[1].error.as_ref().unwrap().contains("Parse error"));
+    assert_eq!(report.endpoints[0].url, format!("{}{}", mock.server.url, QUOTA_PATH));
+}
+
+#[test]
+fn failed_sections_fall_back_to_the_previous_snapshot() {
+    let mut previous = usage_data(vec![token_quota(40.0, Some(600))], 1_000);
+    previous.tool_usage = vec![ToolUsageItem { tool_name: "search".to_string(), usage_count: 3 }];
+    previous.status.quota.fetched_at = Some(1_000);
+    let network = || AppError::Network("timed out".to_string());
+    let models = ModelUsageResult {
+        items: vec![ModelUsageItem { model: "glm-4.6".to_string(), token_count: 10, request_count: 1 }],
+        ..Default::default()
+    };
+
+    let results = FetchResults { quota: Err(network()), models: Ok(models), tools: Err(network()) };
+    let data = results.into_usage_data(Some(&previous), "work", TimeWindow::default(), 2_000).unwrap();
+
+    assert_eq!(data.profile, "work");
+    assert_eq!(data.quota_limits[0].percentage, 40.0);
+    assert_eq!(data.status.quota.fetched_at, Some(1_000));
+    assert_eq!(data.status.quota.error.as_ref().unwrap().kind, "network");
+    assert_eq!(data.model_usage[0].model, "glm-4.6");
+    assert_eq!(data.status.models.fetched_at, Some(2_000));
+    assert_eq!(data.tool_usage[0].tool_name, "search");
+
+    // Tool data for another window is dropped, quota limits are kept
+    let results = FetchResults { quota: Err(network()), models: Ok(ModelUsageResult::default()), tools: Err(network()) };
+    let data = results.into_usage_data(Some(&previous), "work", TimeWindow::Today, 2_000).unwrap();
+    assert!(data.tool_usage.is_empty());
+    assert_eq!(data.status.tools.fetched_at, None);
+    assert_eq!(data.quota_limits.len(), 1);
+}
+
+#[test]
+fn nothing_fetched_is_an_error() {
+    let results = FetchResults {
+        quota: Err(AppError::Unauthorized { status: 401 }),
+        models: Err(AppError::Network("down".to_string())),
+        tools: Err(AppError::Network("down".to_string())),
+    };
+
+    let error = results.into_usage_data(None, "work", TimeWindow::default(), 2_000).unwrap_err();
+
+    assert!(matches!(error, AppError::Unauthorized { status: 401 }));
+}
@@ -0,0 +1,299 @@
+//! Local HTTP stand-in shared by the integration tests
+
+#![allow(dead_code)]
+
+pub mod zai;
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use zai_usage_monitor_lib::types::{
+    AlertMetric, AlertSeverity, AllUsageData, QuotaKind, QuotaLimit, QuotaWindow, RetryPolicy, TimeWindow,
+    UsageAlert, WindowUnit, DEFAULT_PROFILE,
+};
+
+/// A request as received by the stand-in
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    /// Value of a header, matched case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// A scripted response
+#[derive(Debug, Clone)]
+pub struct Reply {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    /// Wait before answering, to simulate a slow server
+    pub delay: Option<Duration>,
+}
+
+impl Reply {
+    pub fn new(status: u16, body: &str) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.to_string(),
+            delay: None,
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+}
+
+/// Scripted replies for every path starting with a prefix
+///
+/// A prefix may end in `?name=value` pairs that the query must also contain.
+type Routes = Arc<Mutex<Vec<(String, VecDeque<Reply>)>>>;
+
+/// HTTP server on a random local port that answers with scripted replies
+///
+/// Replies are used in order; the last one repeats once the script runs out.
+pub struct StandIn {
+    pub url: String,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl StandIn {
+    /// Answer every request from a single script
+    pub async fn start(replies: Vec<Reply>) -> Self {
+        Self::routes(vec![("/", replies)]).await
+    }
+
+    /// Answer each request from the script of the longest matching path prefix
+    pub async fn routes(routes: Vec<(&str, Vec<Reply>)>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let script: Routes = Arc::new(Mutex::new(
+            routes
+                .into_iter()
+                .map(|(prefix, replies)| (prefix.to_string(), VecDeque::from(replies)))
+                .collect(),
+        ));
+
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let recorded = recorded.clone();
+                let script = script.clone();
+                tokio::spawn(async move {
+                    let _ = handle(stream, recorded, script).await;
+                });
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    /// Every request received so far
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+async fn handle(mut stream: TcpStream, recorded: Arc<Mutex<Vec<RecordedRequest>>>, routes: Routes) -> std::io::Result<()> {
+    let request = read_request(&mut stream).await?;
+    let (path, query) = request.path.split_once('?').unwrap_or((&request.path, ""));
+    let params: Vec<String> = query.split('&').map(str::to_string).collect();
+    let path = path.to_string();
+    recorded.lock().unwrap().push(request);
+
+    let reply = {
+        let mut routes = routes.lock().unwrap();
+        routes
+            .iter_mut()
+            .filter(|(route, _)| {
+                let (prefix, wanted) = route.split_once('?').unwrap_or((route, ""));
+                path.starts_with(prefix)
+                    && wanted
+                        .split('&')
+                        .filter(|param| !param.is_empty())
+                        .all(|param| params.iter().any(|p| p == param))
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .and_then(|(_, script)| {
+                if script.len() > 1 {
+                    script.pop_front()
+                } else {
+                    script.front().cloned()
+                }
+            })
+    }
+    .unwrap_or_else(|| Reply::new(404, "no route"));
+
+    if let Some(delay) = reply.delay {
+        tokio::time::sleep(delay).await;
+    }
+
+    let mut response = format!(
+        "HTTP/1.1 {} Stand-in\r\nContent-Length: {}\r\nConnection: close\r\n",
+        reply.status,
+        reply.body.len()
+    );
+    for (name, value) in &reply.headers {
+        response.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    response.push_str("\r\n");
+    response.push_str(&reply.body);
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Read the request head and a `Content-Length` body
+async fn read_request(stream: &mut TcpStream) -> std::io::Result<RecordedRequest> {
+    let mut data = Vec::new();
+    let mut buffer = [0u8; 4096];
+
+    let head_end = loop {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        data.extend_from_slice(&buffer[..read]);
+        if let Some(pos) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&data[..head_end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or_default().to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+
+    let length = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.parse::<usize>().ok())
+        .unwrap_or(0);
+    while data.len() < head_end + length {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        data.extend_from_slice(&buffer[..read]);
+    }
+
+    Ok(RecordedRequest {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&data[head_end..]).to_string(),
+    })
+}
+
+/// Point the app data directory at a fresh temp dir until the guard is dropped
+///
+/// Tests in one binary share the process environment, so they take turns.
+/// `dirs::config_dir` only follows `XDG_CONFIG_HOME` on Linux.
+pub fn isolated_data_dir(name: &str) -> (MutexGuard<'static, ()>, PathBuf) {
+    static ENV: Mutex<()> = Mutex::new(());
+    let guard = ENV.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let home = temp_dir(name);
+    std::env::set_var("XDG_CONFIG_HOME", &home);
+    let data_dir = zai_usage_monitor_lib::config::get_data_dir();
+    std::fs::create_dir_all(&data_dir).unwrap();
+    (guard, data_dir)
+}
+
+/// A fresh, empty directory under the system temp dir
+pub fn temp_dir(name: &str) -> PathBuf {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let dir = std::env::temp_dir().join(format!("zai-usage-test-{}-{}", name, nanos));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Retry quickly and deterministically
+pub fn fast_retries(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy {
+        max_attempts,
+        initial_backoff_ms: 1,
+        max_backoff_ms: 10,
+        multiplier: 1.0,
+        jitter: 0.0,
+    }
+}
+
+/// An alert on the "work" profile's 5-hour token quota; `value` doubles as the percentage used
+pub fn alert(severity: AlertSeverity, metric: AlertMetric, value: f64) -> UsageAlert {
+    UsageAlert {
+        profile: "work".to_string(),
+        rule_id: "rule".to_string(),
+        quota: QuotaKind::Tokens,
+        label: "Token usage (5 Hour)".to_string(),
+        metric,
+        value,
+        threshold: 90.0,
+        percentage: value,
+        severity,
+        timestamp: 1_700_000_000,
+    }
+}
+
+/// A 5-hour token quota at `percentage` used, resetting at 2026-01-01 00:00 UTC
+pub fn token_quota(percentage: f64, remaining: Option<i64>) -> QuotaLimit {
+    QuotaLimit {
+        kind: QuotaKind::Tokens,
+        unit: 3,
+        number: 5,
+        usage: None,
+        current_value: None,
+        remaining,
+        percentage,
+        usage_details: None,
+        next_reset_time: Some(1_767_225_600_000),
+        window: Some(QuotaWindow { unit: WindowUnit::Hour, number: 5 }),
+        window_start: Some(1_767_225_600 - 5 * 3600),
+        resets_at: Some(1_767_225_600),
+    }
+}
+
+/// A refresh of the default profile with only quota data
+pub fn usage_data(quota_limits: Vec<QuotaLimit>, timestamp: i64) -> AllUsageData {
+    AllUsageData {
+        model_usage: Vec::new(),
+        model_usage_timeseries: None,
+        model_usage_timeseries_by_model: Default::default(),
+        tool_usage: Vec::new(),
+        quota_limits,
+        timestamp,
+        time_window: TimeWindow::default(),
+        status: Default::default(),
+        profile: DEFAULT_PROFILE.to_string(),
+    }
+}
@@ -0,0 +1,75 @@
+//! Stand-in for the Z.ai monitor API
+
+use super::{RecordedRequest, Reply, StandIn};
+use std::path::Path;
+use zai_usage_monitor_lib::api::UsageClient;
+use zai_usage_monitor_lib::types::{Config, RetryPolicy};
+
+pub const QUOTA_PATH: &str = "/api/monitor/usage/quota/limit";
+pub const MODEL_USAGE_PATH: &str = "/api/monitor/usage/model-usage";
+pub const TOOL_USAGE_PATH: &str = "/api/monitor/usage/tool-usage";
+
+pub const TOKEN: &str = "test-token";
+
+/// Contents of a file in `tests/fixtures`
+pub fn fixture(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name);
+    std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e))
+}
+
+/// Local monitor API serving the fixtures on every endpoint
+pub struct MockZai {
+    pub server: StandIn,
+}
+
+impl MockZai {
+    /// Serve the fixtures, replacing the script of the endpoints in `overrides`
+    ///
+    /// Model usage filtered by `modelCode` answers from `model_usage_<code>.json`.
+    /// Overriding a path also drops its query-specific routes.
+    pub async fn start(overrides: Vec<(&str, Vec<Reply>)>) -> Self {
+        let mut routes: Vec<(String, Vec<Reply>)> = vec![
+            (QUOTA_PATH.to_string(), vec![Reply::new(200, &fixture("quota_limit.json"))]),
+            (MODEL_USAGE_PATH.to_string(), vec![Reply::new(200, &fixture("model_usage.json"))]),
+            (TOOL_USAGE_PATH.to_string(), vec![Reply::new(200, &fixture("tool_usage.json"))]),
+        ];
+        for model in ["glm-4.6", "glm-4.5-air"] {
+            routes.push((
+                format!("{}?modelCode={}", MODEL_USAGE_PATH, model),
+                vec![Reply::new(200, &fixture(&format!("model_usage_{}.json", model)))],
+            ));
+        }
+        for (path, replies) in overrides {
+            routes.retain(|(route, _)| route.split('?').next() != Some(path));
+            routes.push((path.to_string(), replies));
+        }
+
+        let routes = routes
+            .iter()
+            .map(|(route, replies)| (route.as_str(), replies.clone()))
+            .collect();
+        Self {
+            server: StandIn::routes(routes).await,
+        }
+    }
+
+    /// Client pointed at this server
+    pub fn client(&self, retry: RetryPolicy) -> UsageClient {
+        let config = Config {
+            auth_token: TOKEN.to_string(),
+            retry,
+            monitor_base_url: Some(self.server.url.clone()),
+            ..Config::default()
+        };
+        UsageClient::new(config)
+    }
+
+    /// Requests received for `path`, ignoring the query string
+    pub fn requests_to(&self, path: &str) -> Vec<RecordedRequest> {
+        self.server
+            .requests()
+            .into_iter()
+            .filter(|r| r.path.split('?').next() == Some(path))
+            .collect()
+    }
+}
@@ -0,0 +1,197 @@
+// dirs::config_dir only follows XDG_CONFIG_HOME on Linux
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::isolated_data_dir;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::sync::MutexGuard;
+use zai_usage_monitor_lib::config::{self, ConfigSource, ConfigValueSource, LayeredConfig};
+use zai_usage_monitor_lib::secrets::PASSPHRASE_ENV;
+use zai_usage_monitor_lib::types::Config;
+
+/// A data dir holding `file` over the required fields as config.json, with
+/// no `ZAI_*` overrides set
+fn setup(name: &str, file: serde_json::Value) -> (MutexGuard<'static, ()>, PathBuf) {
+    let (guard, data_dir) = isolated_data_dir(name);
+    for (var, _) in std::env::vars().filter(|(var, _)| var.starts_with("ZAI_")) {
+        std::env::remove_var(var);
+    }
+    // Keep tokens out of the system keyring
+    std::env::set_var(PASSPHRASE_ENV, "correct horse");
+    let mut config = json!({ "base_url": "https://api.z.ai/api/anthropic", "refresh_interval_minutes": 5 });
+    config.as_object_mut().unwrap().extend(file.as_object().unwrap().clone());
+    std::fs::write(data_dir.join("config.json"), config.to_string()).unwrap();
+    (guard, data_dir)
+}
+
+fn load(args: &[&str]) -> LayeredConfig {
+    let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    config::load_layered_config(&args).unwrap()
+}
+
+fn source(sources: &[ConfigValueSource], field: &str) -> (ConfigSource, serde_json::Value) {
+    let entry = sources.iter().find(|s| s.field == field).unwrap();
+    (entry.source, entry.value.clone())
+}
+
+fn read(path: &Path) -> String {
+    std::fs::read_to_string(path).unwrap_or_default()
+}
+
+#[test]
+fn later_layers_take_precedence() {
+    let (_env, _) = setup(
+        "config-precedence",
+        json!({
+            "base_url": "https://file.example.com",
+            "refresh_interval_minutes": 10,
+            "tray_show_worst_case": true,
+        }),
+    );
+    std::env::set_var("ZAI_BASE_URL", "https://env.example.com");
+    std::env::set_var("ZAI_REFRESH_INTERVAL_MINUTES", "20");
+
+    let layered = load(&["--base-url", "https://cli.example.com"]);
+
+    assert!(layered.warnings.is_empty());
+    assert_eq!(layered.config.base_url, "https://cli.example.com");
+    assert_eq!(layered.config.refresh_interval_minutes, 20);
+    assert!(layered.config.tray_show_worst_case);
+    assert_eq!(layered.config.history_retention_days, Config::default().history_retention_days);
+
+    let sources = layered.layers.sources().unwrap();
+    assert_eq!(source(&sources, "base_url"), (ConfigSource::CommandLine, json!("https://cli.example.com")));
+    assert_eq!(source(&sources, "refresh_interval_minutes"), (ConfigSource::Environment, json!(20)));
+    assert_eq!(source(&sources, "tray_show_worst_case"), (ConfigSource::File, json!(true)));
+    assert_eq!(source(&sources, "history_retention_days").0, ConfigSource::Default);
+    // The file layer itself is untouched
+    assert_eq!(layered.layers.file().base_url, "https://file.example.com");
+}
+
+#[test]
+fn both_flag_forms_are_accepted() {
+    let (_env, _) = setup("config-flags", json!({ "base_url": "https://file.example.com" }));
+
+    let layered = load(&[
+        "zai-usage",
+        "--verbose",
+        "--refresh-interval-minutes=25",
+        "--monitor-base-url",
+        "https://monitor.example.com",
+        "--retry={\"max_attempts\": 7}",
+    ]);
+
+    assert!(layered.warnings.is_empty(), "{:?}", layered.warnings);
+    assert_eq!(layered.config.refresh_interval_minutes, 25);
+    assert_eq!(layered.config.monitor_base_url.as_deref(), Some("https://monitor.example.com"));
+    assert_eq!(layered.config.retry.max_attempts, 7);
+    assert_eq!(layered.config.base_url, "https://file.example.com");
+}
+
+#[test]
+fn invalid_overrides_are_skipped_with_a_warning() {
+    let (_env, _) = setup(
+        "config-invalid",
+        json!({ "refresh_interval_minutes": 10, "alert_rules": [], "webhooks": [] }),
+    );
+    std::env::set_var("ZAI_REFRESH_INTERVAL_MINUTES", "soon");
+
+    let layered = load(&["--retry", "{not json", "--tray-show-worst-case", "true"]);
+
+    assert_eq!(layered.warnings.len(), 2, "{:?}", layered.warnings);
+    assert!(layered.warnings[0].contains("ZAI_REFRESH_INTERVAL_MINUTES"));
+    assert!(layered.warnings[1].contains("--retry"));
+    // The rest of the file config and the valid override still apply
+    assert_eq!(layered.config.refresh_interval_minutes, 10);
+    assert!(layered.config.alert_rules.is_empty());
+    assert!(layered.config.tray_show_worst_case);
+
+    let sources = layered.layers.sources().unwrap();
+    assert_eq!(source(&sources, "refresh_interval_minutes").0, ConfigSource::File);
+    assert_eq!(source(&sources, "retry").0, ConfigSource::Default);
+}
+
+#[test]
+fn a_later_invalid_override_keeps_the_earlier_valid_one() {
+    let (_env, _) = setup("config-invalid-cli", json!({}));
+    std::env::set_var("ZAI_REFRESH_INTERVAL_MINUTES", "20");
+
+    let layered = load(&["--refresh-interval-minutes", "-3"]);
+
+    assert_eq!(layered.warnings.len(), 1);
+    assert_eq!(layered.config.refresh_interval_minutes, 20);
+    let sources = layered.layers.sources().unwrap();
+    assert_eq!(source(&sources, "refresh_interval_minutes").0, ConfigSource::Environment);
+}
+
+#[test]
+fn override_tokens_are_never_saved() {
+    let (_env, data_dir) = setup(
+        "config-tokens",
+        json!({ "auth_token": "zai-file-token", "secret_backend": "encrypted_file" }),
+    );
+    std::env::set_var("ZAI_AUTH_TOKEN", "zai-env-token");
+    let mut layered = load(&[]);
+    assert_eq!(layered.config.auth_token, "zai-env-token");
+    assert_eq!(layered.layers.file().auth_token, "zai-file-token");
+
+    // The webview sends back the effective config with an unrelated edit
+    let edited = Config {
+        refresh_interval_minutes: 15,
+        ..layered.config.clone()
+    };
+    let file = layered.layers.without_overrides(&edited).unwrap();
+    assert_eq!(file.auth_token, "zai-file-token");
+    assert_eq!(file.refresh_interval_minutes, 15);
+
+    let saved = config::save_config(&file, layered.layers.file()).unwrap();
+    layered.layers.set_file(saved).unwrap();
+    for path in [data_dir.join("config.json"), data_dir.join("secrets.json")] {
+        assert!(!read(&path).contains("zai-env-token"));
+        assert!(!read(&path).contains("zai-file-token"));
+    }
+    assert_eq!(layered.layers.apply(layered.layers.file()).unwrap().auth_token, "zai-env-token");
+
+    // Without the override the stored file token comes back
+    std::env::remove_var("ZAI_AUTH_TOKEN");
+    assert_eq!(load(&[]).config.auth_token, "zai-file-token");
+}
+
+#[test]
+fn deliberate_edits_to_overridden_fields_are_kept() {
+    let (_env, _) = setup("config-edit-override", json!({ "refresh_interval_minutes": 10 }));
+    std::env::set_var("ZAI_REFRESH_INTERVAL_MINUTES", "20");
+    let layered = load(&[]);
+
+    let edited = Config {
+        refresh_interval_minutes: 30,
+        ..layered.config.clone()
+    };
+    let file = layered.layers.without_overrides(&edited).unwrap();
+
+    assert_eq!(file.refresh_interval_minutes, 30);
+    // The override still wins until it is removed
+    assert_eq!(layered.layers.apply(&file).unwrap().refresh_interval_minutes, 20);
+}
+
+#[test]
+fn sources_mask_tokens() {
+    let (_env, _) = setup(
+        "config-sources",
+        json!({
+            "auth_token": "zai-file-token",
+            "secret_backend": "encrypted_file",
+            "profiles": [{ "name": "work", "auth_token": "zai-work-token", "base_url": "https://api.z.ai/api/anthropic" }],
+        }),
+    );
+
+    let sources = load(&[]).layers.sources().unwrap();
+
+    assert_eq!(source(&sources, "auth_token"), (ConfigSource::File, json!("********")));
+    let (profiles_source, profiles) = source(&sources, "profiles");
+    assert_eq!(profiles_source, ConfigSource::File);
+    assert_eq!(profiles[0]["auth_token"], json!("********"));
+    assert!(!serde_json::to_string(&sources).unwrap().contains("zai-"));
+}
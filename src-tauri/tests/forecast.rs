@@ -0,0 +1,116 @@
+mod common;
+
+use common::{token_quota, usage_data};
+use zai_usage_monitor_lib::forecast::forecast_token_quota;
+use zai_usage_monitor_lib::types::{AllUsageData, ForecastSource, ModelUsageTimeSeries, TimeWindow};
+
+const NOW: i64 = 1_767_220_000;
+
+fn snapshot(percentage: f64, timestamp: i64) -> AllUsageData {
+    usage_data(vec![token_quota(percentage, None)], timestamp)
+}
+
+/// A time series with one bucket per label, the last one still in progress
+fn series(x_time: &[&str], tokens: &[Option<i64>]) -> ModelUsageTimeSeries {
+    ModelUsageTimeSeries {
+        x_time: x_time.iter().map(|t| t.to_string()).collect(),
+        model_call_count: vec![None; tokens.len()],
+        tokens_usage: tokens.to_vec(),
+    }
+}
+
+fn hourly() -> ModelUsageTimeSeries {
+    series(
+        &["2026-01-01 10:00", "2026-01-01 11:00", "2026-01-01 12:00", "2026-01-01 13:00"],
+        &[Some(100_000), Some(200_000), Some(400_000), Some(50_000)],
+    )
+}
+
+#[test]
+fn history_slope_gives_the_burn_rate() {
+    let data = snapshot(40.0, NOW);
+    let history = [snapshot(25.0, NOW - 1800), snapshot(30.0, NOW - 1200)];
+
+    let forecast = forecast_token_quota(&data, &history, NOW).unwrap();
+
+    assert_eq!(forecast.source, ForecastSource::History);
+    assert_eq!(forecast.burn_rate_per_hour, 30.0);
+    // 60 points left at 30 per hour
+    assert_eq!(forecast.exhausted_at, Some(NOW + 2 * 3600));
+    assert!(!forecast.exhausted_before_reset);
+}
+
+#[test]
+fn snapshots_before_a_reset_are_ignored() {
+    let data = snapshot(20.0, NOW);
+    let history = [
+        snapshot(50.0, NOW - 3000),
+        snapshot(60.0, NOW - 2400),
+        snapshot(5.0, NOW - 1800),
+        snapshot(10.0, NOW - 1200),
+    ];
+
+    let forecast = forecast_token_quota(&data, &history, NOW).unwrap();
+
+    // Only 5% -> 20% over the last half hour counts
+    assert_eq!(forecast.burn_rate_per_hour, 30.0);
+}
+
+#[test]
+fn history_needs_ten_minutes_of_snapshots() {
+    let data = snapshot(40.0, NOW);
+
+    assert!(forecast_token_quota(&data, &[snapshot(39.0, NOW - 599)], NOW).is_none());
+
+    let forecast = forecast_token_quota(&data, &[snapshot(39.0, NOW - 600)], NOW).unwrap();
+    assert_eq!(forecast.source, ForecastSource::History);
+    assert_eq!(forecast.burn_rate_per_hour, 6.0);
+}
+
+#[test]
+fn short_history_falls_back_to_the_time_series() {
+    let mut data = snapshot(40.0, NOW);
+    data.quota_limits[0].usage = Some(1_000_000);
+    data.model_usage_timeseries = Some(hourly());
+
+    let forecast = forecast_token_quota(&data, &[snapshot(39.0, NOW - 300)], NOW).unwrap();
+
+    // Average of the two complete hours (200k and 400k tokens) out of a 1M token quota
+    assert_eq!(forecast.source, ForecastSource::Timeseries);
+    assert_eq!(forecast.burn_rate_per_hour, 30.0);
+}
+
+#[test]
+fn tokens_convert_with_the_ratio_used_so_far_without_a_quota_size() {
+    let mut data = snapshot(25.0, NOW);
+    data.quota_limits[0].current_value = Some(500_000);
+    data.model_usage_timeseries = Some(hourly());
+
+    let forecast = forecast_token_quota(&data, &[], NOW).unwrap();
+
+    // 25% for 500k tokens is 0.00005% per token
+    assert_eq!(forecast.burn_rate_per_hour, 15.0);
+
+    // Nothing to convert with
+    data.quota_limits[0].current_value = None;
+    assert!(forecast_token_quota(&data, &[], NOW).is_none());
+}
+
+#[test]
+fn daily_buckets_are_not_used() {
+    let mut data = snapshot(40.0, NOW);
+    data.quota_limits[0].usage = Some(1_000_000);
+    data.time_window = TimeWindow::Last7Days;
+    data.model_usage_timeseries = Some(series(
+        &["2026-01-01 00:00", "2026-01-02 00:00", "2026-01-03 00:00"],
+        &[Some(2_000_000), Some(3_000_000), Some(100_000)],
+    ));
+
+    assert!(forecast_token_quota(&data, &[], NOW).is_none());
+
+    // Unreadable labels are only trusted for the hourly windows
+    data.model_usage_timeseries = Some(series(&["a", "b", "c"], &[Some(1), Some(2), Some(3)]));
+    assert!(forecast_token_quota(&data, &[], NOW).is_none());
+    data.time_window = TimeWindow::Last24Hours;
+    assert!(forecast_token_quota(&data, &[], NOW).is_some());
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use common::{temp_dir, token_quota, usage_data};
+use std::io::Write;
+use zai_usage_monitor_lib::history::HistoryStore;
+use zai_usage_monitor_lib::types::AllUsageData;
+
+const DAY: i64 = 24 * 60 * 60;
+
+fn snapshot(profile: &str, timestamp: i64) -> AllUsageData {
+    let mut data = usage_data(vec![token_quota(10.0, Some(900))], timestamp);
+    data.profile = profile.to_string();
+    data
+}
+
+fn store(name: &str) -> HistoryStore {
+    HistoryStore::new(temp_dir(name).join("history.jsonl"))
+}
+
+fn timestamps(snapshots: &[AllUsageData]) -> Vec<i64> {
+    snapshots.iter().map(|s| s.timestamp).collect()
+}
+
+#[test]
+fn query_filters_by_range_and_profile() {
+    let store = store("history-query");
+    for (profile, timestamp) in [("work", 100), ("home", 200), ("work", 300), ("work", 400)] {
+        store.append(&snapshot(profile, timestamp)).unwrap();
+    }
+
+    assert_eq!(timestamps(&store.query(None, None, None).unwrap()), [100, 200, 300, 400]);
+    assert_eq!(timestamps(&store.query(Some(200), Some(300), None).unwrap()), [200, 300]);
+    assert_eq!(timestamps(&store.query(Some(150), None, Some("work")).unwrap()), [300, 400]);
+    assert_eq!(timestamps(&store.query(None, Some(250), Some("home")).unwrap()), [200]);
+    assert!(store.query(Some(500), None, None).unwrap().is_empty());
+}
+
+#[test]
+fn missing_file_is_an_empty_history() {
+    let store = store("history-missing");
+
+    assert!(store.query(None, None, None).unwrap().is_empty());
+    assert!(store.query(Some(0), None, None).unwrap().is_empty());
+    assert_eq!(store.prune(30).unwrap(), 0);
+}
+
+#[test]
+fn tail_reads_span_chunk_boundaries() {
+    let store = store("history-tail");
+    // Several 64 KiB chunks' worth of snapshots
+    for timestamp in 0..2000 {
+        store.append(&snapshot("work", timestamp * 60)).unwrap();
+    }
+
+    let recent = store.query(Some(1500 * 60), None, None).unwrap();
+    assert_eq!(timestamps(&recent), (1500..2000).map(|t| t * 60).collect::<Vec<_>>());
+    assert_eq!(store.query(Some(0), None, None).unwrap().len(), 2000);
+}
+
+#[test]
+fn out_of_order_appends_are_still_found() {
+    let store = store("history-order");
+    store.append(&snapshot("work", 1000)).unwrap();
+    store.append(&snapshot("home", 1200)).unwrap();
+    // A slower refresh that started earlier finishes last
+    store.append(&snapshot("work", 1100)).unwrap();
+    store.append(&snapshot("home", 1300)).unwrap();
+
+    assert_eq!(timestamps(&store.query(Some(1100), None, Some("work")).unwrap()), [1100]);
+    assert_eq!(timestamps(&store.query(Some(1050), None, None).unwrap()), [1200, 1100, 1300]);
+}
+
+#[test]
+fn unparseable_lines_are_skipped() {
+    let path = temp_dir("history-corrupt").join("history.jsonl");
+    let store = HistoryStore::new(path.clone());
+    store.append(&snapshot("work", 100)).unwrap();
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap()
+        .write_all(b"not json\n\n")
+        .unwrap();
+    store.append(&snapshot("work", 200)).unwrap();
+    // A crash in the middle of a write
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap()
+        .write_all(br#"{"timestamp": 30"#)
+        .unwrap();
+
+    assert_eq!(timestamps(&store.query(None, None, None).unwrap()), [100, 200]);
+    assert_eq!(timestamps(&store.query(Some(0), None, None).unwrap()), [100, 200]);
+}
+
+#[test]
+fn prune_drops_expired_snapshots() {
+    let path = temp_dir("history-prune").join("history.jsonl");
+    let store = HistoryStore::new(path.clone());
+    let now = chrono::Local::now().timestamp();
+    for age in [40, 31, 29, 1] {
+        store.append(&snapshot("work", now - age * DAY)).unwrap();
+    }
+
+    // Retention 0 keeps everything
+    assert_eq!(store.prune(0).unwrap(), 0);
+    assert_eq!(store.prune(30).unwrap(), 2);
+    assert_eq!(timestamps(&store.query(None, None, None).unwrap()), [now - 29 * DAY, now - DAY]);
+
+    // Nothing left to expire, so the file is not rewritten
+    let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+    assert_eq!(store.prune(30).unwrap(), 0);
+    assert_eq!(std::fs::metadata(&path).unwrap().modified().unwrap(), modified);
+
+    store.append(&snapshot("work", now)).unwrap();
+    assert_eq!(store.query(None, None, None).unwrap().len(), 3);
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use common::temp_dir;
+use serde_json::json;
+use zai_usage_monitor_lib::logging::{self, recent_lines, redact, redact_value, rotated_path, RotatingFile};
+use zai_usage_monitor_lib::types::{AlertSeverity, Config, WebhookConfig};
+
+#[test]
+fn configured_secrets_are_redacted() {
+    let config = Config {
+        auth_token: "zai-test-token-1234".to_string(),
+        webhooks: vec![WebhookConfig {
+            id: "slack".to_string(),
+            url: "https://hooks.slack.com/services/T000/B000/XXXX".to_string(),
+            preset: Default::default(),
+            template: None,
+            secret: Some("hook-secret".to_string()),
+            min_severity: AlertSeverity::Info,
+            enabled: true,
+        }],
+        ..Config::default()
+    };
+    logging::configure(&config);
+
+    let line = redact("sent zai-test-token-1234 to https://hooks.slack.com/services/T000/B000/XXXX signed with hook-secret");
+
+    assert_eq!(line, "sent [REDACTED] to [REDACTED] signed with [REDACTED]");
+}
+
+#[test]
+fn credentials_and_personal_data_are_redacted() {
+    assert_eq!(redact("Authorization: Bearer abc.def-123"), "Authorization: [REDACTED]");
+    assert_eq!(redact(r#"{"email":"jane@example.com","id":7}"#), r#"{"email":"[REDACTED]","id":7}"#);
+    assert_eq!(redact("token=abc123&page=2"), "token=[REDACTED]&page=2");
+    assert_eq!(redact("contact jane@example.com"), "contact [EMAIL]");
+    assert_eq!(redact("key sk-abcdefgh12345"), "key [REDACTED]");
+    assert_eq!(redact("HTTP 500: internal error"), "HTTP 500: internal error");
+}
+
+#[test]
+fn sensitive_json_keys_are_redacted() {
+    let mut value = json!({
+        "auth_token": "abc",
+        "profiles": [{ "name": "work", "authToken": "def", "auth_token_ref": "keyring:profile:work" }],
+        "userEmail": "x",
+        "tokens_usage": [1, 2],
+        "secret": null,
+    });
+
+    redact_value(&mut value);
+
+    assert_eq!(
+        value,
+        json!({
+            "auth_token": "[REDACTED]",
+            "profiles": [{ "name": "work", "authToken": "[REDACTED]", "auth_token_ref": "keyring:profile:work" }],
+            "userEmail": "[REDACTED]",
+            "tokens_usage": [1, 2],
+            "secret": null,
+        })
+    );
+}
+
+#[test]
+fn log_files_rotate_and_keep_the_newest_lines() {
+    let path = temp_dir("logging").join("app.log");
+    let mut file = RotatingFile::new(path.clone(), 20, 3);
+
+    for i in 0..10 {
+        file.write_line(&format!("line {:04}", i)).unwrap();
+    }
+
+    // Two 10-byte lines fit per file; only three files are kept
+    assert!(rotated_path(&path, 2).exists());
+    assert!(!rotated_path(&path, 3).exists());
+    assert_eq!(
+        recent_lines(&path, 10),
+        vec!["line 0004", "line 0005", "line 0006", "line 0007", "line 0008", "line 0009"]
+    );
+    assert_eq!(recent_lines(&path, 2), vec!["line 0008", "line 0009"]);
+}
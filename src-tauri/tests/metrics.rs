@@ -0,0 +1,68 @@
+mod common;
+
+use common::{token_quota, usage_data};
+use std::collections::BTreeMap;
+use zai_usage_monitor_lib::metrics::render;
+use zai_usage_monitor_lib::types::{AllUsageData, Config, ModelUsageItem, ToolUsageItem, DEFAULT_PROFILE};
+
+fn usage() -> AllUsageData {
+    let mut quota = token_quota(42.5, None);
+    quota.current_value = Some(425);
+
+    let mut data = usage_data(vec![quota], 1_767_220_000);
+    data.model_usage = vec![ModelUsageItem {
+        model: "glm \"4.6\"\\beta\nnext".to_string(),
+        token_count: 420_000,
+        request_count: 30,
+    }];
+    data.tool_usage = vec![ToolUsageItem {
+        tool_name: "search-prime".to_string(),
+        usage_count: 5,
+    }];
+    data
+}
+
+#[test]
+fn render_writes_the_exposition_format() {
+    let config = Config {
+        monitor_base_url: Some("https://monitor.example.com".to_string()),
+        ..Config::default()
+    };
+    let usage_by_profile = BTreeMap::from([(DEFAULT_PROFILE.to_string(), usage())]);
+
+    let output = render(&config, &usage_by_profile);
+    let lines: Vec<&str> = output.lines().collect();
+
+    let labels = r#"profile="default",base_domain="https://monitor.example.com""#;
+    for expected in [
+        "# HELP zai_quota_percentage Quota used, in percent".to_string(),
+        "# TYPE zai_quota_percentage gauge".to_string(),
+        format!(r#"zai_quota_percentage{{{},type="TOKENS_LIMIT"}} 42.5"#, labels),
+        format!(r#"zai_quota_current_value{{{},type="TOKENS_LIMIT"}} 425"#, labels),
+        format!(r#"zai_quota_next_reset_timestamp_seconds{{{},type="TOKENS_LIMIT"}} 1767225600"#, labels),
+        format!(r#"zai_quota_window_seconds{{{},type="TOKENS_LIMIT"}} 18000"#, labels),
+        "# TYPE zai_model_tokens gauge".to_string(),
+        format!(r#"zai_tool_calls{{{},window="last_24_hours",tool="search-prime"}} 5"#, labels),
+        format!("zai_last_refresh_timestamp_seconds{{{}}} 1767220000", labels),
+    ] {
+        assert!(lines.contains(&expected.as_str()), "missing {:?} in\n{}", expected, output);
+    }
+
+    // Families without samples still get their metadata, and nothing is a counter
+    assert!(lines.contains(&"# TYPE zai_quota_remaining gauge"));
+    assert!(!lines.iter().any(|line| line.contains("_total")));
+    assert!(!lines.iter().any(|line| line.starts_with("# TYPE") && !line.ends_with(" gauge")));
+}
+
+#[test]
+fn render_escapes_label_values() {
+    let usage_by_profile = BTreeMap::from([(DEFAULT_PROFILE.to_string(), usage())]);
+
+    let output = render(&Config::default(), &usage_by_profile);
+
+    let tokens = output
+        .lines()
+        .find(|line| line.starts_with("zai_model_tokens{"))
+        .unwrap();
+    assert!(tokens.ends_with(r#",window="last_24_hours",model="glm \"4.6\"\\beta\nnext"} 420000"#), "{}", tokens);
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use common::alert;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use zai_usage_monitor_lib::notifications::{
+    alert_notification, quota_available_notification, NotificationAction, NotificationCenter,
+    RecordingNotifier,
+};
+use zai_usage_monitor_lib::types::{AlertMetric, AlertSeverity};
+
+fn center() -> (NotificationCenter, Arc<RecordingNotifier>) {
+    let recorder = Arc::new(RecordingNotifier::new());
+    (NotificationCenter::new(Box::new(recorder.clone())), recorder)
+}
+
+#[test]
+fn alert_notifications_offer_dashboard_and_snooze() {
+    let notification = alert_notification(&alert(AlertSeverity::Critical, AlertMetric::Percentage, 91.34));
+
+    assert_eq!(notification.title, "🚨 Critical Usage Alert");
+    assert_eq!(notification.body, "Token usage (5 Hour) (work): 91.3% used");
+    assert_eq!(
+        notification.actions,
+        vec![NotificationAction::OpenDashboard, NotificationAction::Snooze]
+    );
+}
+
+#[test]
+fn body_describes_the_metric_that_fired() {
+    let remaining = alert_notification(&alert(AlertSeverity::Warning, AlertMetric::Remaining, 120.0));
+    assert!(remaining.body.ends_with("120 remaining"));
+    assert_eq!(remaining.title, "⚠️ Usage Warning");
+
+    let burn = alert_notification(&alert(AlertSeverity::Info, AlertMetric::BurnRate, 33.333));
+    assert!(burn.body.ends_with("burning 33.3%/h"));
+}
+
+#[test]
+fn open_dashboard_action_runs_the_callback() {
+    let (center, recorder) = center();
+    let opened = Arc::new(AtomicBool::new(false));
+
+    let flag = opened.clone();
+    let shown = center
+        .notify_alert(&alert(AlertSeverity::Critical, AlertMetric::Percentage, 95.0), 0, move || {
+            flag.store(true, Ordering::SeqCst)
+        })
+        .unwrap();
+
+    assert!(shown);
+    assert!(recorder.respond(0, NotificationAction::OpenDashboard));
+    assert!(opened.load(Ordering::SeqCst));
+    // Each notification can only be answered once
+    assert!(!recorder.respond(0, NotificationAction::OpenDashboard));
+}
+
+#[test]
+fn snooze_action_silences_further_notifications() {
+    let (center, recorder) = center();
+    let now = chrono::Local::now().timestamp();
+    let alert = alert(AlertSeverity::Warning, AlertMetric::Percentage, 75.0);
+
+    assert!(center.notify_alert(&alert, now, || {}).unwrap());
+    assert!(recorder.respond(0, NotificationAction::Snooze));
+    assert!(center.is_snoozed(now));
+
+    assert!(!center.notify_alert(&alert, now, || {}).unwrap());
+    assert_eq!(recorder.notifications().len(), 1);
+
+    // Notifications resume once the hour is over
+    let later = center.snoozed_until(now).unwrap() + 1;
+    assert!(center.notify_alert(&alert, later, || {}).unwrap());
+    assert_eq!(recorder.notifications().len(), 2);
+}
+
+#[test]
+fn quota_available_notification_respects_snooze() {
+    let (center, recorder) = center();
+    let now = chrono::Local::now().timestamp();
+
+    assert!(center.notify_quota_available("work", now, || {}).unwrap());
+    assert_eq!(recorder.notifications(), vec![quota_available_notification("work")]);
+    assert!(recorder.notifications()[0].body.contains("work"));
+
+    assert!(recorder.respond(0, NotificationAction::Snooze));
+    assert!(!center.notify_quota_available("work", now, || {}).unwrap());
+}
+
+#[test]
+fn backend_responses_map_to_actions() {
+    assert_eq!(NotificationAction::from_response("snooze"), Some(NotificationAction::Snooze));
+    assert_eq!(NotificationAction::from_response("Snooze 1h"), Some(NotificationAction::Snooze));
+    assert_eq!(
+        NotificationAction::from_response("default"),
+        Some(NotificationAction::OpenDashboard)
+    );
+    assert_eq!(NotificationAction::from_response("__closed"), None);
+}
@@ -0,0 +1,146 @@
+mod common;
+
+use common::{isolated_data_dir, temp_dir};
+use zai_usage_monitor_lib::config;
+use zai_usage_monitor_lib::error::AppError;
+use zai_usage_monitor_lib::secrets::{EncryptedFileStore, SecretBackend, SecretStore, PASSPHRASE_ENV};
+use zai_usage_monitor_lib::types::Config;
+
+#[test]
+fn encrypted_file_round_trip() {
+    let path = temp_dir("secrets").join("secrets.json");
+    let store = EncryptedFileStore::new(path.clone(), "correct horse".to_string());
+
+    store.set("profile:work", "zai-work-token").unwrap();
+    store.set("profile:home", "zai-home-token").unwrap();
+
+    assert_eq!(store.get("profile:work").unwrap().as_deref(), Some("zai-work-token"));
+    assert_eq!(store.get("profile:missing").unwrap(), None);
+    assert!(!std::fs::read_to_string(&path).unwrap().contains("zai-work-token"));
+
+    store.delete("profile:work").unwrap();
+    assert_eq!(store.get("profile:work").unwrap(), None);
+    assert_eq!(store.get("profile:home").unwrap().as_deref(), Some("zai-home-token"));
+}
+
+#[test]
+fn wrong_passphrase_is_a_storage_error() {
+    let path = temp_dir("secrets").join("secrets.json");
+    EncryptedFileStore::new(path.clone(), "correct horse".to_string())
+        .set("profile:default", "zai-token")
+        .unwrap();
+
+    let error = EncryptedFileStore::new(path, "battery staple".to_string())
+        .get("profile:default")
+        .unwrap_err();
+
+    assert!(matches!(error, AppError::Storage(msg) if msg.contains("wrong passphrase")));
+}
+
+#[test]
+fn wrong_passphrase_cannot_add_entries() {
+    let path = temp_dir("secrets").join("secrets.json");
+    let store = EncryptedFileStore::new(path.clone(), "correct horse".to_string());
+    store.set("profile:default", "zai-token").unwrap();
+    let before = std::fs::read_to_string(&path).unwrap();
+
+    let error = EncryptedFileStore::new(path.clone(), "battery staple".to_string())
+        .set("profile:work", "zai-work-token")
+        .unwrap_err();
+
+    assert!(matches!(error, AppError::Storage(msg) if msg.contains("wrong passphrase")));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), before);
+    assert_eq!(store.get("profile:default").unwrap().as_deref(), Some("zai-token"));
+}
+
+#[test]
+fn truncated_nonce_is_a_storage_error() {
+    let path = temp_dir("secrets").join("secrets.json");
+    let store = EncryptedFileStore::new(path.clone(), "correct horse".to_string());
+    store.set("profile:default", "zai-token").unwrap();
+
+    let mut file: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    file["entries"]["profile:default"]["nonce"] = "AAAA".into();
+    std::fs::write(&path, file.to_string()).unwrap();
+
+    let error = store.get("profile:default").unwrap_err();
+
+    assert!(matches!(error, AppError::Storage(msg) if msg.contains("nonce")));
+}
+
+#[cfg(unix)]
+#[test]
+fn secrets_file_is_private() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = temp_dir("secrets").join("secrets.json");
+    std::fs::write(&path, r#"{"salt":"c2FsdHNhbHRzYWx0c2FsdA==","entries":{}}"#).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+    EncryptedFileStore::new(path.clone(), "correct horse".to_string())
+        .set("profile:default", "zai-token")
+        .unwrap();
+
+    assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn plaintext_tokens_are_migrated_to_the_store() {
+    let (_env, data_dir) = isolated_data_dir("secrets-migration");
+    std::env::set_var(PASSPHRASE_ENV, "correct horse");
+
+    let plaintext = Config {
+        auth_token: "zai-plaintext-token".to_string(),
+        secret_backend: SecretBackend::EncryptedFile,
+        ..Config::default()
+    };
+    std::fs::write(data_dir.join("config.json"), serde_json::to_string(&plaintext).unwrap()).unwrap();
+
+    let loaded = config::load_config().unwrap();
+
+    assert_eq!(loaded.auth_token, "zai-plaintext-token");
+    let on_disk = std::fs::read_to_string(data_dir.join("config.json")).unwrap();
+    assert!(!on_disk.contains("zai-plaintext-token"));
+    let saved: Config = serde_json::from_str(&on_disk).unwrap();
+    assert_eq!(saved.auth_token_ref.as_deref(), Some("file:profile:default"));
+
+    // Loading again reads the token back from the encrypted file
+    assert_eq!(config::load_config().unwrap().auth_token, "zai-plaintext-token");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn unchanged_tokens_are_not_stored_again() {
+    let (_env, data_dir) = isolated_data_dir("secrets-unchanged");
+    std::env::set_var(PASSPHRASE_ENV, "correct horse");
+    let secrets_file = || std::fs::read_to_string(data_dir.join("secrets.json")).unwrap();
+
+    let config = Config {
+        auth_token: "zai-default-token".to_string(),
+        secret_backend: SecretBackend::EncryptedFile,
+        ..Config::default()
+    };
+    let saved = config::save_config(&config, &Config::default()).unwrap();
+
+    // The returned config keeps the token and gains its reference
+    assert_eq!(saved.auth_token, "zai-default-token");
+    assert_eq!(saved.auth_token_ref.as_deref(), Some("file:profile:default"));
+    let stored = secrets_file();
+
+    // Every write uses a fresh nonce, so an untouched file means no write
+    let edited = Config {
+        refresh_interval_minutes: 15,
+        ..saved.clone()
+    };
+    let saved = config::save_config(&edited, &saved).unwrap();
+    assert_eq!(secrets_file(), stored);
+
+    let rotated = Config {
+        auth_token: "zai-rotated-token".to_string(),
+        ..saved.clone()
+    };
+    config::save_config(&rotated, &saved).unwrap();
+    assert_ne!(secrets_file(), stored);
+    assert_eq!(config::load_config().unwrap().auth_token, "zai-rotated-token");
+}
@@ -0,0 +1,60 @@
+use zai_usage_monitor_lib::tray_icon::{band_color, render_ring, ICON_SIZE};
+
+const GREEN: [u8; 4] = [0x10, 0xB9, 0x81, 0xFF];
+const YELLOW: [u8; 4] = [0xEA, 0xB3, 0x08, 0xFF];
+const AMBER: [u8; 4] = [0xF5, 0x9E, 0x0B, 0xFF];
+const RED: [u8; 4] = [0xEF, 0x44, 0x44, 0xFF];
+const TRACK: [u8; 4] = [128, 128, 128, 90];
+
+/// Just right of 12 o'clock and just right of 6 o'clock, both well inside the ring
+const TOP: (u32, u32) = (16, 1);
+const BOTTOM: (u32, u32) = (16, 30);
+
+fn pixel(pixels: &[u8], (x, y): (u32, u32)) -> [u8; 4] {
+    let offset = ((y * ICON_SIZE + x) * 4) as usize;
+    pixels[offset..offset + 4].try_into().unwrap()
+}
+
+fn ring(percentage: Option<f64>) -> Vec<u8> {
+    render_ring(percentage, ICON_SIZE)
+}
+
+#[test]
+fn bands_change_at_50_70_and_90_percent() {
+    assert_eq!(band_color(0.0), GREEN);
+    assert_eq!(band_color(49.9), GREEN);
+    assert_eq!(band_color(50.0), YELLOW);
+    assert_eq!(band_color(69.9), YELLOW);
+    assert_eq!(band_color(70.0), AMBER);
+    assert_eq!(band_color(89.9), AMBER);
+    assert_eq!(band_color(90.0), RED);
+    assert_eq!(band_color(150.0), RED);
+}
+
+#[test]
+fn ring_fills_clockwise_from_12_oclock() {
+    // Anything above 0% starts covering the top pixel
+    assert_ne!(pixel(&ring(Some(1.0)), TOP), TRACK);
+    assert_eq!(pixel(&ring(Some(5.0)), TOP), GREEN);
+    assert_eq!(pixel(&ring(Some(0.0)), TOP), TRACK);
+
+    // The bottom is halfway round
+    assert_eq!(pixel(&ring(Some(40.0)), BOTTOM), TRACK);
+    assert_eq!(pixel(&ring(Some(60.0)), BOTTOM), YELLOW);
+    assert_eq!(pixel(&ring(Some(100.0)), BOTTOM), RED);
+    // Overflowing percentages fill the ring and nothing more
+    assert_eq!(ring(Some(250.0)), ring(Some(100.0)));
+}
+
+#[test]
+fn no_usage_draws_only_the_track() {
+    let pixels = ring(None);
+
+    assert_eq!(pixels.len(), (ICON_SIZE * ICON_SIZE * 4) as usize);
+    assert_eq!(pixel(&pixels, TOP), TRACK);
+    assert_eq!(pixel(&pixels, BOTTOM), TRACK);
+    // Only the ring is drawn; the center and corners stay transparent
+    assert_eq!(pixel(&pixels, (16, 16)), [0, 0, 0, 0]);
+    assert_eq!(pixel(&pixels, (0, 0)), [0, 0, 0, 0]);
+    assert!(pixels.chunks(4).all(|p| p[3] == 0 || p[..3] == TRACK[..3]));
+}
@@ -0,0 +1,128 @@
+mod common;
+
+use common::{token_quota, usage_data};
+use zai_usage_monitor_lib::error::AppError;
+use zai_usage_monitor_lib::types::{AllUsageData, ForecastSource, QuotaForecast, QuotaKind, SectionError};
+use zai_usage_monitor_lib::tray_title::{TitleContext, TrayTemplate, DEFAULT_TEMPLATE};
+
+/// 5600 seconds before the token quota resets
+const NOW: i64 = 1_767_220_000;
+
+/// Token quota at 42.4% with 575 left, MCP quota at 12.6% with nothing reported
+fn data() -> AllUsageData {
+    let mut token = token_quota(42.4, Some(575));
+    token.current_value = Some(425);
+    let mut mcp = token_quota(12.6, None);
+    mcp.kind = QuotaKind::Time;
+    mcp.resets_at = None;
+
+    let mut data = usage_data(vec![token, mcp], NOW);
+    data.profile = "work".to_string();
+    data
+}
+
+fn render(template: &str, data: &AllUsageData, forecast: Option<&QuotaForecast>) -> String {
+    let context = TitleContext {
+        data,
+        percentages: (42.4, 12.6),
+        forecast,
+        now: NOW,
+    };
+    TrayTemplate::parse(template).unwrap().render(&context)
+}
+
+fn parse_error(template: &str) -> String {
+    match TrayTemplate::parse(template) {
+        Err(AppError::InvalidInput(message)) => message,
+        other => panic!("expected an invalid input error for {:?}, got {:?}", template, other),
+    }
+}
+
+#[test]
+fn default_template_shows_both_quotas() {
+    let mut data = data();
+    assert_eq!(render(DEFAULT_TEMPLATE, &data, None), "🆉 T:42% M:13%");
+
+    data.status.quota.error = Some(SectionError {
+        kind: "network".to_string(),
+        message: "timed out".to_string(),
+    });
+    assert_eq!(render(DEFAULT_TEMPLATE, &data, None), "🆉 T:42% M:13% ⚠");
+}
+
+#[test]
+fn placeholders_render_quota_fields() {
+    let data = data();
+
+    assert_eq!(
+        render("{profile}: {token.used} used, {token.remaining} left, resets in {token.reset}", &data, None),
+        "work: 425 used, 575 left, resets in 1h 34m"
+    );
+    // Fields the API didn't report
+    assert_eq!(render("{mcp.remaining}/{mcp.used}/{mcp.reset}", &data, None), "-/-/-");
+}
+
+#[test]
+fn doubled_braces_are_literal() {
+    let data = data();
+
+    assert_eq!(render("{{token.pct}}", &data, None), "{token.pct}");
+    assert_eq!(render("{{{token.pct}}}", &data, None), "{42}");
+}
+
+#[test]
+fn nested_conditions_need_every_level_to_hold() {
+    let data = data();
+    let template = "T{if token.pct > 40}!{if mcp.pct > 50} M{end}{if mcp.pct < 50} m{end}{end}";
+
+    assert_eq!(render(template, &data, None), "T! m");
+    assert_eq!(render("{if token.pct > 50}a{if mcp.pct < 50}b{end}{end}c", &data, None), "c");
+}
+
+#[test]
+fn two_character_operators_take_precedence() {
+    let data = data();
+
+    assert_eq!(render("{if token.pct >= 42.4}ge{end}", &data, None), "ge");
+    assert_eq!(render("{if token.pct <= 42.4}le{end}", &data, None), "le");
+    assert_eq!(render("{if token.pct != 42.4}ne{end}", &data, None), "");
+    assert_eq!(render("{if token.pct == 42.4}eq{end}", &data, None), "eq");
+    assert_eq!(render("{if token.pct>42.4}gt{end}{if token.pct<42.4}lt{end}", &data, None), "");
+}
+
+#[test]
+fn presence_conditions_test_for_a_value() {
+    let data = data();
+    let forecast = QuotaForecast {
+        profile: "work".to_string(),
+        percentage: 42.4,
+        burn_rate_per_hour: 60.0,
+        source: ForecastSource::History,
+        exhausted_at: Some(NOW + 2520),
+        resets_at: Some(NOW + 5600),
+        exhausted_before_reset: true,
+        computed_at: NOW,
+    };
+    let template = "{if forecast}out in {forecast}{end}{if mcp.remaining}?{end}";
+
+    assert_eq!(render(template, &data, Some(&forecast)), "out in 42m");
+    assert_eq!(render(template, &data, None), "");
+}
+
+#[test]
+fn unknown_placeholders_are_rejected_with_the_available_keys() {
+    let message = parse_error("T:{token.percent}%");
+
+    assert!(message.starts_with("Invalid tray title template: unknown placeholder {token.percent}"), "{}", message);
+    assert!(message.contains("available: token.pct, token.remaining"), "{}", message);
+    assert!(parse_error("{if tokens > 5}x{end}").contains("unknown placeholder {tokens}"));
+}
+
+#[test]
+fn malformed_templates_are_rejected() {
+    assert!(parse_error("{if token.pct > 5}x").contains("{if} without {end}"));
+    assert!(parse_error("x{end}").contains("{end} without {if}"));
+    assert!(parse_error("x}").contains("unmatched '}'"));
+    assert!(parse_error("{token.pct").contains("unclosed '{'"));
+    assert!(parse_error("{if token.pct > high}x{end}").contains("expected a number"));
+}
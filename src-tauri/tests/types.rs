@@ -0,0 +1,90 @@
+use chrono::{TimeZone, Utc};
+use serde_json::json;
+use zai_usage_monitor_lib::types::{QuotaKind, QuotaLimit, QuotaWindow, WindowUnit};
+
+/// A quota limit as sent by the API, decoded
+fn decoded(value: serde_json::Value) -> QuotaLimit {
+    let mut limit: QuotaLimit = serde_json::from_value(value).unwrap();
+    limit.decode();
+    limit
+}
+
+fn utc(year: i32, month: u32, day: u32) -> i64 {
+    Utc.with_ymd_and_hms(year, month, day, 0, 0, 0).unwrap().timestamp()
+}
+
+#[test]
+fn hour_windows_end_at_the_next_reset() {
+    let limit = decoded(json!({
+        "type": "TOKENS_LIMIT", "unit": 3, "number": 5, "percentage": 10,
+        "nextResetTime": 1_767_225_600_999_i64,
+    }));
+
+    assert_eq!(limit.kind, QuotaKind::Tokens);
+    assert_eq!(limit.window, Some(QuotaWindow { unit: WindowUnit::Hour, number: 5 }));
+    // Milliseconds are truncated to whole seconds
+    assert_eq!(limit.resets_at, Some(1_767_225_600));
+    assert_eq!(limit.window_start, Some(1_767_225_600 - 5 * 3600));
+}
+
+#[test]
+fn minute_and_day_windows() {
+    let minutes = decoded(json!({ "type": "TIME_LIMIT", "unit": 2, "number": 30, "percentage": 0, "nextResetTime": 1_000_000 }));
+    assert_eq!(minutes.window, Some(QuotaWindow { unit: WindowUnit::Minute, number: 30 }));
+    assert_eq!(minutes.window_start, Some(1_000 - 30 * 60));
+
+    let days = decoded(json!({ "type": "TIME_LIMIT", "unit": 4, "number": 7, "percentage": 0, "nextResetTime": 1_000_000 }));
+    assert_eq!(days.window, Some(QuotaWindow { unit: WindowUnit::Day, number: 7 }));
+    assert_eq!(days.window_start, Some(1_000 - 7 * 86_400));
+}
+
+#[test]
+fn month_windows_are_calendar_months() {
+    let reset = utc(2026, 2, 1);
+    let limit = decoded(json!({ "type": "TIME_LIMIT", "unit": 5, "number": 1, "percentage": 12, "nextResetTime": reset * 1000 }));
+
+    assert_eq!(limit.window, Some(QuotaWindow { unit: WindowUnit::Month, number: 1 }));
+    assert_eq!(limit.window_start, Some(utc(2026, 1, 1)));
+
+    // A month before March 31st is the last day of February
+    let reset = utc(2026, 3, 31);
+    let limit = decoded(json!({ "type": "TIME_LIMIT", "unit": 5, "number": 1, "percentage": 12, "nextResetTime": reset * 1000 }));
+    assert_eq!(limit.window_start, Some(utc(2026, 2, 28)));
+
+    // Three-month windows
+    let limit = decoded(json!({ "type": "TIME_LIMIT", "unit": 5, "number": 3, "percentage": 12, "nextResetTime": reset * 1000 }));
+    assert_eq!(limit.window_start, Some(utc(2025, 12, 31)));
+}
+
+#[test]
+fn unknown_units_keep_the_reset_but_have_no_start() {
+    let limit = decoded(json!({
+        "type": "TOKENS_LIMIT", "unit": 9, "number": 2, "percentage": 10,
+        "nextResetTime": 1_767_225_600_000_i64,
+    }));
+
+    assert_eq!(limit.window, Some(QuotaWindow { unit: WindowUnit::Unknown, number: 2 }));
+    assert_eq!(limit.window.unwrap().approx_secs(), None);
+    assert_eq!(limit.resets_at, Some(1_767_225_600));
+    assert_eq!(limit.window_start, None);
+}
+
+#[test]
+fn missing_reset_time_leaves_reset_and_start_empty() {
+    let limit = decoded(json!({ "type": "TOKENS_LIMIT", "unit": 3, "number": 5, "percentage": 10 }));
+
+    assert_eq!(limit.window, Some(QuotaWindow { unit: WindowUnit::Hour, number: 5 }));
+    assert_eq!(limit.resets_at, None);
+    assert_eq!(limit.window_start, None);
+
+    let limit = decoded(json!({ "type": "TOKENS_LIMIT", "unit": 3, "number": 5, "percentage": 10, "nextResetTime": null }));
+    assert_eq!(limit.resets_at, None);
+}
+
+#[test]
+fn unknown_quota_types_are_kept() {
+    let limit = decoded(json!({ "type": "IMAGE_LIMIT", "unit": 4, "number": 1, "percentage": 3 }));
+
+    assert_eq!(limit.kind, QuotaKind::Unknown("IMAGE_LIMIT".to_string()));
+    assert_eq!(limit.kind.as_raw(), "IMAGE_LIMIT");
+}
@@ -0,0 +1,224 @@
+mod common;
+
+use common::{alert, fast_retries, temp_dir, Reply, StandIn};
+use hmac::{Hmac, Mac};
+use serde_json::json;
+use sha2::Sha256;
+use zai_usage_monitor_lib::types::{AlertMetric, AlertSeverity, WebhookConfig, WebhookPreset};
+use zai_usage_monitor_lib::webhooks::{render_body, WebhookDispatcher};
+
+fn webhook(url: &str, preset: WebhookPreset) -> WebhookConfig {
+    WebhookConfig {
+        id: "team".to_string(),
+        url: url.to_string(),
+        preset,
+        template: None,
+        secret: None,
+        min_severity: AlertSeverity::Info,
+        enabled: true,
+    }
+}
+
+fn dispatcher(name: &str) -> WebhookDispatcher {
+    WebhookDispatcher::new(temp_dir(name).join("deliveries.jsonl"))
+}
+
+#[test]
+fn presets_shape_the_body_for_each_service() {
+    let alert = alert(AlertSeverity::Critical, AlertMetric::Percentage, 92.5);
+
+    let slack = render_body(&webhook("http://x", WebhookPreset::Slack), &alert);
+    assert_eq!(
+        slack,
+        json!({ "text": "[CRITICAL] Token usage (5 Hour) (work): 92.5% used" })
+    );
+
+    let discord = render_body(&webhook("http://x", WebhookPreset::Discord), &alert);
+    assert!(discord["content"].as_str().unwrap().starts_with("[CRITICAL]"));
+
+    let teams = render_body(&webhook("http://x", WebhookPreset::Teams), &alert);
+    assert_eq!(teams["@type"], "MessageCard");
+    assert_eq!(teams["themeColor"], "E74C3C");
+
+    let generic = render_body(&webhook("http://x", WebhookPreset::Generic), &alert);
+    assert_eq!(generic["alert"]["percentage"], json!(92.5));
+    assert_eq!(generic["alert"]["severity"], "critical");
+}
+
+#[test]
+fn templates_substitute_placeholders_and_keep_numbers_typed() {
+    let mut hook = webhook("http://x", WebhookPreset::Generic);
+    hook.template = Some(json!({
+        "summary": "{{profile}} at {{percentage}}%",
+        "value": "{{value}}",
+        "tags": ["{{severity}}", "static"]
+    }));
+
+    let body = render_body(&hook, &alert(AlertSeverity::Warning, AlertMetric::Percentage, 92.5));
+    assert_eq!(
+        body,
+        json!({
+            "summary": "work at 92.5%",
+            "value": 92.5,
+            "tags": ["warning", "static"]
+        })
+    );
+}
+
+#[tokio::test]
+async fn delivers_signed_body_and_logs_success() {
+    let server = StandIn::start(vec![Reply::new(200, "ok")]).await;
+    let dispatcher = dispatcher("signed");
+    let mut hook = webhook(&server.url, WebhookPreset::Slack);
+    hook.secret = Some("s3cret".to_string());
+
+    let delivery = dispatcher
+        .deliver(&hook, &alert(AlertSeverity::Critical, AlertMetric::Percentage, 92.5), &fast_retries(3))
+        .await;
+
+    assert!(delivery.success);
+    assert_eq!(delivery.attempts, 1);
+    assert_eq!(delivery.status, Some(200));
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    let request = &requests[0];
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.header("content-type"), Some("application/json"));
+
+    let timestamp = request.header("x-zai-timestamp").unwrap();
+    let mut mac = Hmac::<Sha256>::new_from_slice(b"s3cret").unwrap();
+    mac.update(format!("{}.{}", timestamp, request.body).as_bytes());
+    let expected: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    assert_eq!(request.header("x-zai-signature"), Some(format!("sha256={}", expected).as_str()));
+
+    let log = dispatcher.deliveries(10).unwrap();
+    assert_eq!(log.len(), 1);
+    assert!(log[0].success);
+    assert_eq!(log[0].webhook_id, "team");
+}
+
+#[tokio::test]
+async fn unsigned_webhooks_send_no_signature() {
+    let server = StandIn::start(vec![Reply::new(204, "")]).await;
+    let dispatcher = dispatcher("unsigned");
+
+    let delivery = dispatcher
+        .deliver(&webhook(&server.url, WebhookPreset::Generic), &alert(AlertSeverity::Info, AlertMetric::Percentage, 92.5), &fast_retries(1))
+        .await;
+
+    assert!(delivery.success);
+    assert_eq!(server.requests()[0].header("x-zai-signature"), None);
+}
+
+#[tokio::test]
+async fn retries_server_errors_until_success() {
+    let server = StandIn::start(vec![
+        Reply::new(500, "boom"),
+        Reply::new(503, "busy"),
+        Reply::new(200, "ok"),
+    ])
+    .await;
+    let dispatcher = dispatcher("retry");
+
+    let delivery = dispatcher
+        .deliver(&webhook(&server.url, WebhookPreset::Discord), &alert(AlertSeverity::Warning, AlertMetric::Percentage, 92.5), &fast_retries(5))
+        .await;
+
+    assert!(delivery.success);
+    assert_eq!(delivery.attempts, 3);
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn gives_up_on_client_errors_and_logs_the_failure() {
+    let server = StandIn::start(vec![Reply::new(400, "bad payload")]).await;
+    let dispatcher = dispatcher("client-error");
+
+    let delivery = dispatcher
+        .deliver(&webhook(&server.url, WebhookPreset::Teams), &alert(AlertSeverity::Warning, AlertMetric::Percentage, 92.5), &fast_retries(5))
+        .await;
+
+    assert!(!delivery.success);
+    assert_eq!(delivery.attempts, 1);
+    assert_eq!(delivery.status, Some(400));
+    assert!(delivery.error.unwrap().contains("bad payload"));
+
+    let log = dispatcher.deliveries(10).unwrap();
+    assert_eq!(log.len(), 1);
+    assert!(!log[0].success);
+}
+
+#[tokio::test]
+async fn exhausted_retries_are_logged_with_the_attempt_count() {
+    let server = StandIn::start(vec![Reply::new(502, "gateway")]).await;
+    let dispatcher = dispatcher("exhausted");
+
+    let delivery = dispatcher
+        .deliver(&webhook(&server.url, WebhookPreset::Slack), &alert(AlertSeverity::Critical, AlertMetric::Percentage, 92.5), &fast_retries(2))
+        .await;
+
+    assert!(!delivery.success);
+    assert_eq!(delivery.attempts, 2);
+    assert_eq!(delivery.status, Some(502));
+}
+
+#[tokio::test]
+async fn dispatch_skips_disabled_and_lower_severity_webhooks() {
+    let server = StandIn::start(vec![Reply::new(200, "ok")]).await;
+    let dispatcher = dispatcher("dispatch");
+
+    let mut critical_only = webhook(&server.url, WebhookPreset::Slack);
+    critical_only.id = "critical-only".to_string();
+    critical_only.min_severity = AlertSeverity::Critical;
+
+    let mut disabled = webhook(&server.url, WebhookPreset::Slack);
+    disabled.id = "disabled".to_string();
+    disabled.enabled = false;
+
+    let everything = webhook(&server.url, WebhookPreset::Slack);
+
+    let deliveries = dispatcher
+        .dispatch(
+            &[critical_only, disabled, everything],
+            &alert(AlertSeverity::Warning, AlertMetric::Percentage, 92.5),
+            &fast_retries(1),
+        )
+        .await;
+
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0].webhook_id, "team");
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn logged_errors_leave_out_the_url_and_long_bodies() {
+    let dispatcher = dispatcher("redacted");
+    // Nothing listens on port 9, so the request fails before any response
+    let hook = webhook("http://127.0.0.1:9/services/T000/B000/s3cretpath", WebhookPreset::Slack);
+
+    let delivery = dispatcher
+        .deliver(&hook, &alert(AlertSeverity::Critical, AlertMetric::Percentage, 92.5), &fast_retries(1))
+        .await;
+
+    assert!(!delivery.success);
+    assert!(!delivery.error.unwrap().contains("s3cretpath"));
+
+    let body = format!("echo {}", "x".repeat(5000));
+    let server = StandIn::start(vec![Reply::new(400, &body)]).await;
+    let delivery = dispatcher
+        .deliver(&webhook(&server.url, WebhookPreset::Slack), &alert(AlertSeverity::Critical, AlertMetric::Percentage, 92.5), &fast_retries(1))
+        .await;
+
+    let error = delivery.error.unwrap();
+    assert!(error.starts_with("HTTP 400: echo xxx"), "{}", error);
+    assert!(error.len() < 300, "{}", error);
+
+    let log = dispatcher.deliveries(10).unwrap();
+    assert!(log.iter().all(|d| !d.error.as_deref().unwrap_or_default().contains("s3cretpath")));
+}
@@ -1,4 +1,4 @@
-import { useEffect, useState, useCallback, useRef } from 'react';
+import { useEffect, useState, useCallback } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { listen } from '@tauri-apps/api/event';
 import { BarChart3, Settings, AlertTriangle, Key, RefreshCw, Loader2 } from 'lucide-react';
@@ -7,11 +7,14 @@ import { Alert, AlertDescription } from '@/components/ui/alert';
 import UsageDisplay from './components/UsageDisplay';
 import UsageDetails from './components/UsageDetails';
 import SettingsModal from './components/Settings';
+import { errorMessage, isAppError } from '@/lib/utils';
 
 interface Config {
   auth_token: string;
   base_url: string;
   refresh_interval_minutes: number;
+  tray_title_template?: string;
+  monitor_base_url?: string | null;
 }
 
 interface QuotaLimit {
@@ -23,6 +26,10 @@ interface QuotaLimit {
   unit?: number;
   number?: number;
   usageDetails?: Array<{ tool_name: string; usage: number }>;
+  nextResetTime?: number;
+  window?: { unit: 'minute' | 'hour' | 'day' | 'month' | 'unknown'; number: number };
+  windowStart?: number;
+  resetsAt?: number;
 }
 
 interface ModelUsageItem {
@@ -42,18 +49,25 @@ interface ToolUsageItem {
   usage_count: number;
 }
 
+interface SectionStatus {
+  fetched_at: number | null;
+  error: { kind: string; message: string } | null;
+}
+
+interface UsageStatus {
+  quota: SectionStatus;
+  models: SectionStatus;
+  tools: SectionStatus;
+}
+
 interface AllUsageData {
   model_usage: ModelUsageItem[];
   model_usage_timeseries?: ModelUsageTimeSeries;
+  model_usage_timeseries_by_model?: Record<string, ModelUsageTimeSeries>;
   tool_usage: ToolUsageItem[];
   quota_limits: QuotaLimit[];
   timestamp: number;
-}
-
-interface UsageAlert {
-  type: string;
-  percentage: number;
-  severity: 'warning' | 'critical';
+  status?: UsageStatus;
 }
 
 function App() {
@@ -67,7 +81,6 @@ function App() {
   const [error, setError] = useState<string | null>(null);
   const [showSettings, setShowSettings] = useState(false);
   const [needsConfig, setNeedsConfig] = useState(false);
-  const intervalRef = useRef<number | null>(null);
 
   const fetchUsageData = useCallback(async () => {
     if (!config.auth_token) {
@@ -83,9 +96,8 @@ function App() {
       setUsageData(data);
       setNeedsConfig(false);
     } catch (err: unknown) {
-      const errorMessage = err instanceof Error ? err.message : String(err);
-      setError(errorMessage);
-      if (errorMessage.includes('401') || errorMessage.includes('403')) {
+      setError(errorMessage(err));
+      if (isAppError(err) && (err.kind === 'unauthorized' || err.kind === 'not_configured')) {
         setNeedsConfig(true);
       }
     } finally {
@@ -122,52 +134,12 @@ function App() {
     fetchUsageData();
   }, [fetchUsageData]);
 
+  // Background refreshes are scheduled by the Rust side; just render what it pushes
   useEffect(() => {
-    if (config.auth_token) {
-      intervalRef.current = window.setInterval(() => {
-        fetchUsageData();
-      }, config.refresh_interval_minutes * 60 * 1000);
-
-      return () => {
-        if (intervalRef.current) {
-          clearInterval(intervalRef.current);
-        }
-      };
-    }
-  }, [config, fetchUsageData]);
-
-  useEffect(() => {
-    const unlisten = listen('refresh-requested', () => {
-      fetchUsageData();
-    });
-
-    return () => {
-      unlisten.then(fn => fn());
-    };
-  }, [fetchUsageData]);
-
-  // Request notification permission on mount
-  useEffect(() => {
-    if ('Notification' in window && Notification.permission === 'default') {
-      Notification.requestPermission();
-    }
-  }, []);
-
-  // Listen for usage alerts and show notifications
-  useEffect(() => {
-    const unlisten = listen<UsageAlert>('usage-alert', (event) => {
-      const { type, percentage, severity } = event.payload;
-
-      if ('Notification' in window && Notification.permission === 'granted') {
-        const title = severity === 'critical'
-          ? '🚨 Critical Usage Alert'
-          : '⚠️ Usage Warning';
-
-        new Notification(title, {
-          body: `${type}: ${percentage.toFixed(1)}% used`,
-          icon: '/icon.png',
-        });
-      }
+    const unlisten = listen<AllUsageData>('usage-data-updated', (event) => {
+      setUsageData(event.payload);
+      setError(null);
+      setNeedsConfig(false);
     });
 
     return () => {
@@ -245,6 +217,28 @@ function App() {
           </Alert>
         )}
 
+        {/* Partial Failure Display */}
+        {usageData?.status && !error && (() => {
+          const sections: Array<[string, SectionStatus]> = [
+            ['Quota', usageData.status.quota],
+            ['Models', usageData.status.models],
+            ['Tools', usageData.status.tools],
+          ];
+          const failed = sections.filter(([, status]) => status.error);
+          if (failed.length === 0) return null;
+          return (
+            <Alert className="mb-3 backdrop-blur-sm">
+              <AlertTriangle className="h-3.5 w-3.5" />
+              <AlertDescription>
+                <p className="font-semibold text-xs">Showing older data for {failed.map(([name]) => name).join(', ')}</p>
+                {failed.map(([name, status]) => (
+                  <p key={name} className="text-[10px] break-words">{name}: {status.error?.message}</p>
+                ))}
+              </AlertDescription>
+            </Alert>
+          );
+        })()}
+
         {/* Loading State */}
         {loading && !usageData && (
           <div className="bg-white/80 dark:bg-slate-900/80 backdrop-blur-xl rounded-lg shadow border border-slate-200/50 dark:border-slate-700/50 p-6 text-center">
@@ -1,6 +1,6 @@
 import React, { useState } from 'react';
 import { invoke } from '@tauri-apps/api/core';
-import { Settings as SettingsIcon, Key, Globe, Timer, Loader2, FlaskRound, Save } from 'lucide-react';
+import { Settings as SettingsIcon, Key, Globe, Server, Timer, Type, Loader2, FlaskRound, Save, CheckCircle2, XCircle, FileDown } from 'lucide-react';
 import {
   Dialog,
   DialogContent,
@@ -10,21 +10,36 @@ import {
 import { Button } from '@/components/ui/button';
 import { Input } from '@/components/ui/input';
 import { Label } from '@/components/ui/label';
-import {
-  Select,
-  SelectContent,
-  SelectItem,
-  SelectTrigger,
-  SelectValue,
-} from '@/components/ui/select';
 import { Alert, AlertDescription } from '@/components/ui/alert';
+import { errorMessage } from '@/lib/utils';
 
 interface Config {
   auth_token: string;
   base_url: string;
   refresh_interval_minutes: number;
+  tray_title_template?: string;
+  monitor_base_url?: string | null;
+}
+
+interface EndpointProbe {
+  endpoint: string;
+  url: string;
+  ok: boolean;
+  status?: number | null;
+  error?: string | null;
+  duration_ms: number;
 }
 
+interface BaseUrlValidation {
+  monitor_root: string;
+  endpoints: EndpointProbe[];
+}
+
+const KNOWN_BASE_URLS = [
+  'https://api.z.ai/api/anthropic',
+  'https://open.bigmodel.cn/api/anthropic',
+];
+
 interface SettingsProps {
   config: Config;
   onSave: () => void;
@@ -35,30 +50,46 @@ const SettingsModal: React.FC<SettingsProps> = ({ config, onSave, onClose }) =>
   const [authToken, setAuthToken] = useState(config.auth_token || '');
   const [baseUrl, setBaseUrl] = useState(config.base_url || 'https://api.z.ai/api/anthropic');
   const [refreshInterval, setRefreshInterval] = useState(config.refresh_interval_minutes || 5);
+  const [monitorBaseUrl, setMonitorBaseUrl] = useState(config.monitor_base_url || '');
+  const [validation, setValidation] = useState<BaseUrlValidation | null>(null);
+  const [trayTitleTemplate, setTrayTitleTemplate] = useState(config.tray_title_template || '');
   const [saving, setSaving] = useState(false);
   const [testing, setTesting] = useState(false);
   const [error, setError] = useState<string | null>(null);
+  const [diagnosticsPath, setDiagnosticsPath] = useState<string | null>(null);
 
   const handleTest = async () => {
     setTesting(true);
     setError(null);
+    setValidation(null);
 
     try {
-      await invoke('save_config', {
-        authToken,
+      // Probe the endpoints with the values in the form, without saving them
+      const result = await invoke<BaseUrlValidation>('validate_base_url', {
         baseUrl,
-        refreshIntervalMinutes: refreshInterval,
+        monitorBaseUrl,
+        authToken,
       });
-
-      await invoke('get_usage_data');
-      alert('✅ Connection successful!');
+      setValidation(result);
     } catch (err: unknown) {
-      setError(err instanceof Error ? err.message : String(err));
+      setError(errorMessage(err));
     } finally {
       setTesting(false);
     }
   };
 
+  const handleExportDiagnostics = async () => {
+    setError(null);
+    setDiagnosticsPath(null);
+
+    try {
+      // Written with tokens, secrets and personal fields redacted
+      setDiagnosticsPath(await invoke<string>('export_diagnostics'));
+    } catch (err: unknown) {
+      setError(errorMessage(err));
+    }
+  };
+
   const handleSave = async () => {
     setSaving(true);
     setError(null);
@@ -68,10 +99,12 @@ const SettingsModal: React.FC<SettingsProps> = ({ config, onSave, onClose }) =>
         authToken,
         baseUrl,
         refreshIntervalMinutes: refreshInterval,
+        trayTitleTemplate: trayTitleTemplate || null,
+        monitorBaseUrl,
       });
       onSave();
     } catch (err: unknown) {
-      setError(err instanceof Error ? err.message : String(err));
+      setError(errorMessage(err));
     } finally {
       setSaving(false);
     }
@@ -114,19 +147,36 @@ const SettingsModal: React.FC<SettingsProps> = ({ config, onSave, onClose }) =>
               <Globe className="w-4 h-4" />
               Base URL
             </Label>
-            <Select value={baseUrl} onValueChange={setBaseUrl}>
-              <SelectTrigger id="base-url" className="font-mono text-sm">
-                <SelectValue />
-              </SelectTrigger>
-              <SelectContent>
-                <SelectItem value="https://api.z.ai/api/anthropic" className="font-mono text-sm">
-                  https://api.z.ai/api/anthropic
-                </SelectItem>
-                <SelectItem value="https://open.bigmodel.cn/api/anthropic" className="font-mono text-sm">
-                  https://open.bigmodel.cn/api/anthropic
-                </SelectItem>
-              </SelectContent>
-            </Select>
+            <Input
+              id="base-url"
+              type="url"
+              list="known-base-urls"
+              value={baseUrl}
+              onChange={(e) => setBaseUrl(e.target.value)}
+              className="font-mono text-sm"
+            />
+            <datalist id="known-base-urls">
+              {KNOWN_BASE_URLS.map((url) => (
+                <option key={url} value={url} />
+              ))}
+            </datalist>
+            <p className="text-xs text-slate-500 dark:text-slate-400">Z.ai, BigModel or any proxy or mirror URL</p>
+          </div>
+
+          <div className="space-y-2">
+            <Label htmlFor="monitor-base-url" className="flex items-center gap-2">
+              <Server className="w-4 h-4" />
+              Monitor URL
+            </Label>
+            <Input
+              id="monitor-base-url"
+              type="url"
+              value={monitorBaseUrl}
+              onChange={(e) => setMonitorBaseUrl(e.target.value)}
+              placeholder="Derived from the base URL"
+              className="font-mono text-sm"
+            />
+            <p className="text-xs text-slate-500 dark:text-slate-400">Optional root of the usage monitor API, if it differs</p>
           </div>
 
           <div className="space-y-2">
@@ -149,6 +199,50 @@ const SettingsModal: React.FC<SettingsProps> = ({ config, onSave, onClose }) =>
             <p className="text-xs text-slate-500 dark:text-slate-400">How often to fetch usage data</p>
           </div>
 
+          <div className="space-y-2">
+            <Label htmlFor="tray-title" className="flex items-center gap-2">
+              <Type className="w-4 h-4" />
+              Tray Title
+            </Label>
+            <Input
+              id="tray-title"
+              type="text"
+              value={trayTitleTemplate}
+              onChange={(e) => setTrayTitleTemplate(e.target.value)}
+              placeholder="🆉 T:{token.pct}% M:{mcp.pct}%"
+              className="font-mono text-sm"
+            />
+            <p className="text-xs text-slate-500 dark:text-slate-400">
+              Placeholders like {'{token.pct}'} or {'{forecast}'}; {'{if mcp.pct > 50}'}...{'{end}'} shows a part conditionally
+            </p>
+          </div>
+
+          {validation && (
+            <div className="space-y-1.5 rounded-md border border-slate-200 dark:border-slate-700 p-3">
+              <p className="text-xs text-slate-500 dark:text-slate-400 font-mono break-all">{validation.monitor_root}</p>
+              {validation.endpoints.map((probe) => (
+                <div key={probe.endpoint} className="flex items-start gap-2 text-sm" title={probe.url}>
+                  {probe.ok ? (
+                    <CheckCircle2 className="w-4 h-4 mt-0.5 text-emerald-500 flex-shrink-0" />
+                  ) : (
+                    <XCircle className="w-4 h-4 mt-0.5 text-red-500 flex-shrink-0" />
+                  )}
+                  <div className="min-w-0">
+                    <p className="font-medium">
+                      {probe.endpoint}
+                      <span className="ml-2 text-xs text-slate-500 dark:text-slate-400">
+                        {probe.status ?? '-'} · {probe.duration_ms} ms
+                      </span>
+                    </p>
+                    {probe.error && (
+                      <p className="text-xs text-red-600 dark:text-red-400 break-words">{probe.error}</p>
+                    )}
+                  </div>
+                </div>
+              ))}
+            </div>
+          )}
+
           {error && (
             <Alert variant="destructive">
               <AlertDescription>
@@ -198,6 +292,16 @@ const SettingsModal: React.FC<SettingsProps> = ({ config, onSave, onClose }) =>
             )}
           </Button>
         </div>
+
+        <div className="flex flex-col items-center gap-1">
+          <Button onClick={handleExportDiagnostics} variant="ghost" size="sm" className="text-xs">
+            <FileDown className="w-3.5 h-3.5 mr-1.5" />
+            Export diagnostics
+          </Button>
+          {diagnosticsPath && (
+            <p className="text-xs text-slate-500 dark:text-slate-400 break-all text-center">Saved to {diagnosticsPath}</p>
+          )}
+        </div>
       </DialogContent>
     </Dialog>
   );
@@ -12,22 +12,44 @@ interface QuotaLimit {
   number?: number;
   usageDetails?: Array<{ tool_name: string; usage: number }>;
   nextResetTime?: number;
+  window?: QuotaWindow;
+  windowStart?: number;
+  resetsAt?: number;
 }
 
+interface QuotaWindow {
+  unit: 'minute' | 'hour' | 'day' | 'month' | 'unknown';
+  number: number;
+}
+
+const WINDOW_UNITS: Record<QuotaWindow['unit'], string> = {
+  minute: 'min',
+  hour: 'hr',
+  day: 'd',
+  month: 'mo',
+  unknown: '',
+};
+
 interface UsageDisplayProps {
   quotaLimits: QuotaLimit[];
 }
 
 const UsageDisplay: React.FC<UsageDisplayProps> = ({ quotaLimits }) => {
+  const formatWindow = (quotaWindow?: QuotaWindow): string => {
+    if (!quotaWindow || quotaWindow.unit === 'unknown') return '';
+    return `${quotaWindow.number}${WINDOW_UNITS[quotaWindow.unit]}`;
+  };
+
+  // resetsAt is in Unix seconds
   const formatResetTime = (timestamp?: number): string | null => {
     if (!timestamp) return null;
-    const date = new Date(timestamp);
+    const date = new Date(timestamp * 1000);
     return date.toLocaleTimeString([], { hour: '2-digit', minute: '2-digit' });
   };
 
   const formatResetDateTime = (timestamp?: number): string | null => {
     if (!timestamp) return null;
-    const date = new Date(timestamp);
+    const date = new Date(timestamp * 1000);
     const now = new Date();
     const isToday = date.toDateString() === now.toDateString();
 
@@ -59,21 +81,21 @@ const UsageDisplay: React.FC<UsageDisplayProps> = ({ quotaLimits }) => {
     return 'text-emerald-700 dark:text-emerald-200';
   };
 
-  const tokenLimit = quotaLimits.find(l => l.type.toUpperCase().includes('TOKEN'));
-  const mcpLimit = quotaLimits.find(l => l.type.toUpperCase().includes('MCP'));
+  const tokenLimit = quotaLimits.find(l => l.type === 'TOKENS_LIMIT');
+  const mcpLimit = quotaLimits.find(l => l.type === 'TIME_LIMIT');
 
   return (
     <div className="bg-white/80 dark:bg-slate-900/80 backdrop-blur-xl rounded-lg shadow border border-slate-200/50 dark:border-slate-700/50 p-2">
       <div className="flex gap-2">
         {tokenLimit && (
-          <div className={`flex-1 p-2 rounded-md border ${getBgGradient(tokenLimit.percentage)} backdrop-blur-sm transition-all duration-300`} title={formatResetDateTime(tokenLimit.nextResetTime) || ''}>
+          <div className={`flex-1 p-2 rounded-md border ${getBgGradient(tokenLimit.percentage)} backdrop-blur-sm transition-all duration-300`} title={formatResetDateTime(tokenLimit.resetsAt) || ''}>
             <div className="flex items-center justify-between gap-2 mb-1.5">
               <div className="flex items-center gap-1.5 min-w-0">
                 <Zap className="w-3.5 h-3.5 text-amber-500 flex-shrink-0" />
                 <div className="flex flex-col min-w-0">
                   <span className="text-[10px] font-semibold text-slate-800 dark:text-slate-200 truncate">Tokens</span>
                   <span className="text-[9px] text-slate-600 dark:text-slate-400">
-                    {formatResetTime(tokenLimit.nextResetTime) || '5hr'}
+                    {formatResetTime(tokenLimit.resetsAt) || formatWindow(tokenLimit.window)}
                   </span>
                 </div>
               </div>
@@ -101,7 +123,7 @@ const UsageDisplay: React.FC<UsageDisplayProps> = ({ quotaLimits }) => {
                 <Wrench className="w-3.5 h-3.5 text-blue-500 flex-shrink-0" />
                 <div className="flex flex-col min-w-0">
                   <span className="text-[10px] font-semibold text-slate-800 dark:text-slate-200 truncate">MCP</span>
-                  <span className="text-[9px] text-slate-600 dark:text-slate-400">1mo</span>
+                  <span className="text-[9px] text-slate-600 dark:text-slate-400">{formatWindow(mcpLimit.window)}</span>
                 </div>
               </div>
               <span className={`text-sm font-bold flex-shrink-0 ${getTextColor(mcpLimit.percentage)}`}>
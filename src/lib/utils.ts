@@ -4,3 +4,20 @@ import { twMerge } from "tailwind-merge"
 export function cn(...inputs: ClassValue[]) {
   return twMerge(clsx(inputs))
 }
+
+/** Error shape returned by Tauri commands (`AppError` on the Rust side) */
+export interface AppError {
+  kind: string
+  message: string
+  /** Set when a retryable request failed after several attempts */
+  attempts?: number | null
+}
+
+export function isAppError(err: unknown): err is AppError {
+  return typeof err === "object" && err !== null && "kind" in err && "message" in err
+}
+
+export function errorMessage(err: unknown): string {
+  if (isAppError(err)) return err.message
+  return err instanceof Error ? err.message : String(err)
+}